@@ -6,9 +6,14 @@ fn read_day_from_args() -> Option<u8> {
     env::args().nth(1).and_then(|arg| arg.parse().ok())
 }
 
+fn read_options_from_args() -> puzzles::Options {
+    env::args().skip(2).collect()
+}
+
 fn main() {
     let puzzles = puzzles::puzzles();
     if let Some(puzzle) = read_day_from_args().and_then(|day| puzzles.get(&day)) {
-        puzzle.execute()
+        puzzle.execute();
+        puzzle.handle_options(&read_options_from_args());
     }
 }
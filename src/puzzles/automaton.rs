@@ -0,0 +1,217 @@
+use itertools::Itertools;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use std::hash::Hash;
+
+pub trait CellularAutomaton {
+    fn step(&self) -> Self;
+
+    fn population(&self) -> usize;
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Cycle {
+    pub start: usize,
+    pub period: usize,
+}
+
+impl Cycle {
+    pub fn is_steady_state(&self) -> bool {
+        self.period == 1
+    }
+}
+
+impl Display for Cycle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_steady_state() {
+            write!(f, "steady state from generation {}", self.start)
+        } else {
+            write!(
+                f,
+                "cycle of period {} from generation {}",
+                self.period, self.start
+            )
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Simulation<A> {
+    current: A,
+    generation: usize,
+    history: Vec<usize>,
+    seen: HashMap<A, usize>,
+    cycle: Option<Cycle>,
+}
+
+impl<A: CellularAutomaton + Clone + Eq + Hash> Simulation<A> {
+    pub fn new(initial: A) -> Self {
+        Self {
+            history: vec![initial.population()],
+            seen: HashMap::from([(initial.clone(), 0)]),
+            current: initial,
+            generation: 0,
+            cycle: None,
+        }
+    }
+
+    pub fn current(&self) -> &A {
+        &self.current
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn history(&self) -> &[usize] {
+        &self.history
+    }
+
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.cycle
+    }
+
+    pub fn step(&mut self) -> Option<Cycle> {
+        self.current = self.current.step();
+        self.generation += 1;
+        self.history.push(self.current.population());
+        if self.cycle.is_none() {
+            if let Some(&start) = self.seen.get(&self.current) {
+                self.cycle = Some(Cycle {
+                    start,
+                    period: self.generation - start,
+                });
+                self.seen.clear();
+            } else {
+                self.seen.insert(self.current.clone(), self.generation);
+            }
+        }
+        self.cycle
+    }
+
+    pub fn run(&mut self, generations: usize) -> Option<Cycle> {
+        for _ in 0..generations {
+            self.step();
+        }
+        self.cycle
+    }
+
+    pub fn run_until_cycle(&mut self, limit: usize) -> Option<Cycle> {
+        while self.cycle.is_none() && self.generation < limit {
+            self.step();
+        }
+        self.cycle
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Default)]
+pub struct Life(BTreeSet<(i32, i32)>);
+
+impl Life {
+    pub fn new(cells: impl IntoIterator<Item = (i32, i32)>) -> Self {
+        Self(cells.into_iter().collect())
+    }
+}
+
+impl CellularAutomaton for Life {
+    fn step(&self) -> Self {
+        let neighbours = self
+            .0
+            .iter()
+            .flat_map(|&(x, y)| {
+                (-1..=1)
+                    .cartesian_product(-1..=1)
+                    .filter(|&offset| offset != (0, 0))
+                    .map(move |(dx, dy)| (x + dx, y + dy))
+            })
+            .counts();
+        Self(
+            neighbours
+                .into_iter()
+                .filter(|(cell, count)| *count == 3 || (*count == 2 && self.0.contains(cell)))
+                .map(|(cell, _)| cell)
+                .collect(),
+        )
+    }
+
+    fn population(&self) -> usize {
+        self.0.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse_life(input: &str) -> Life {
+        Life::new(input.lines().enumerate().flat_map(|(y, line)| {
+            line.chars()
+                .enumerate()
+                .filter(|(_, c)| *c == '#')
+                .map(move |(x, _)| (x as i32, y as i32))
+        }))
+    }
+
+    #[test]
+    fn blinker_should_oscillate_with_period_2() {
+        let mut simulation = Simulation::new(parse_life("...\n###\n..."));
+
+        let cycle = simulation.run_until_cycle(10);
+
+        assert_eq!(
+            cycle,
+            Some(Cycle {
+                start: 0,
+                period: 2
+            })
+        );
+        assert_eq!(simulation.generation(), 2);
+        assert_eq!(simulation.history(), &[3, 3, 3]);
+    }
+
+    #[test]
+    fn block_should_be_a_steady_state() {
+        let mut simulation = Simulation::new(parse_life("##\n##"));
+
+        let cycle = simulation.run_until_cycle(10).unwrap();
+
+        assert!(cycle.is_steady_state());
+        assert_eq!(cycle.to_string(), "steady state from generation 0");
+    }
+
+    #[test]
+    fn glider_should_move_without_repeating() {
+        let glider = parse_life(".#.\n..#\n###");
+        let mut simulation = Simulation::new(glider.clone());
+
+        let cycle = simulation.run_until_cycle(20);
+
+        assert_eq!(cycle, None);
+        assert_eq!(simulation.generation(), 20);
+        assert!(simulation
+            .history()
+            .iter()
+            .all(|population| *population == 5));
+        assert_eq!(
+            simulation.current(),
+            &Life::new(glider.0.iter().map(|(x, y)| (x + 5, y + 5)))
+        );
+    }
+
+    #[test]
+    fn run_should_keep_stepping_after_a_cycle_is_found() {
+        let mut simulation = Simulation::new(parse_life("#"));
+
+        let cycle = simulation.run(5);
+
+        assert_eq!(
+            cycle,
+            Some(Cycle {
+                start: 1,
+                period: 1
+            })
+        );
+        assert_eq!(simulation.generation(), 5);
+        assert_eq!(simulation.history(), &[1, 0, 0, 0, 0, 0]);
+    }
+}
@@ -34,7 +34,7 @@ struct DigitCount(Vec<(u32, u32)>);
 
 impl DigitCount {
     fn count(input: &[String]) -> Self {
-        let size = input.get(0).map(|s| s.len()).unwrap_or(0);
+        let size = input.first().map(|s| s.len()).unwrap_or(0);
         let initial_count = Self((0..size).map(|_| (0, 0)).collect());
         input
             .iter()
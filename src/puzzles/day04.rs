@@ -49,7 +49,7 @@ fn play_to_first_win<const N: usize>(
     drawn_numbers: &[u32],
     boards: &[Board<N>],
 ) -> Option<(Board<N>, u32)> {
-    let mut boards = boards.iter().copied().collect::<Vec<_>>();
+    let mut boards = boards.to_vec();
     for number in drawn_numbers {
         boards.iter_mut().for_each(|board| board.mark(*number));
         if let Some(winning_board) = boards.iter().find(|board| board.has_won()) {
@@ -64,7 +64,7 @@ fn play_to_end<const N: usize>(
     boards: &[Board<N>],
 ) -> Option<(Board<N>, u32)> {
     let mut result = None;
-    let mut boards = boards.iter().copied().collect::<Vec<_>>();
+    let mut boards = boards.to_vec();
     for number in drawn_numbers {
         boards.iter_mut().for_each(|board| board.mark(*number));
         let (winning_boards, remaining_boards) =
@@ -146,7 +146,8 @@ impl<const N: usize> Board<N> {
             .iter()
             .flat_map(|line| {
                 line.iter()
-                    .filter_map(|square| (!square.marked).then(|| square.number))
+                    .filter(|square| !square.marked)
+                    .map(|square| square.number)
             })
             .collect()
     }
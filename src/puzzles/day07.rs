@@ -35,7 +35,7 @@ fn compute_alignment_position(input: &[u32]) -> u32 {
     let mut positions = input.to_vec();
     positions.sort_unstable();
     positions
-        .get((positions.len() + 1) / 2 - 1)
+        .get(positions.len().div_ceil(2) - 1)
         .copied()
         .unwrap_or(0)
 }
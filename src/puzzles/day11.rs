@@ -4,7 +4,10 @@ use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
-use super::Puzzle;
+use super::automaton::{CellularAutomaton, Cycle, Simulation};
+use super::{Options, Puzzle};
+
+const STEP_LIMIT: usize = 10_000;
 
 const INPUT: &str = r"4764745784
 4643457176
@@ -31,24 +34,82 @@ impl Puzzle for Day11 {
     }
 
     fn part_two(&self) -> String {
-        let mut octopuses: OctopusGroup<10> = INPUT.parse().unwrap();
-        let step = find_first_synchronized_flashing_step(&mut octopuses);
+        let octopuses: OctopusGroup<10> = INPUT.parse().unwrap();
+        let step = find_first_synchronized_flashing_step(&octopuses, STEP_LIMIT)
+            .expect("Octopuses should synchronize");
         format!("First synchronized flashing step: {}", step)
     }
-}
 
-fn find_first_synchronized_flashing_step<const N: usize>(octopuses: &mut OctopusGroup<N>) -> usize {
-    let mut i = 0;
-    loop {
-        i += 1;
-        let flashes = octopuses.next_step();
-        if flashes == N * N {
-            break i;
+    fn handle_options(&self, options: &Options) {
+        if let Some(generations) = options.value("simulate") {
+            let generations = generations.parse().expect("Generations should be a number");
+            let mut simulation = Simulation::new(INPUT.parse::<OctopusGroup<10>>().unwrap());
+            let cycle = simulation.run_until_cycle(generations);
+            println!(
+                "Flashes per step: {}",
+                simulation.history().iter().skip(1).join(", ")
+            );
+            match cycle {
+                Some(cycle) => println!("Octopuses reach a {}", cycle),
+                None => println!("No cycle within {} steps", generations),
+            }
+        }
+
+        if options.has("flashing") {
+            let octopuses: OctopusGroup<10> = INPUT.parse().unwrap();
+            match octopuses.analyze_flashing(STEP_LIMIT) {
+                Ok(FlashingReport {
+                    cycle,
+                    first_synchronization,
+                }) => {
+                    println!("Octopuses repeat with a {}", cycle);
+                    match first_synchronization {
+                        Some(step) => println!("First synchronized flashing step: {}", step),
+                        None => println!("Octopuses never flash in sync"),
+                    }
+                }
+                Err(error) => println!("{:?}", error),
+            }
         }
     }
 }
 
+fn find_first_synchronized_flashing_step<const N: usize>(
+    octopuses: &OctopusGroup<N>,
+    limit: usize,
+) -> Result<usize, FlashingError> {
+    match octopuses.analyze_flashing(limit) {
+        Ok(FlashingReport {
+            first_synchronization: Some(step),
+            ..
+        })
+        | Err(FlashingError::StepLimitExceeded {
+            first_synchronization: Some(step),
+            ..
+        }) => Ok(step),
+        Ok(FlashingReport { cycle, .. }) => Err(FlashingError::NeverSynchronized { cycle }),
+        Err(error) => Err(error),
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct FlashingReport {
+    cycle: Cycle,
+    first_synchronization: Option<usize>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum FlashingError {
+    StepLimitExceeded {
+        limit: usize,
+        first_synchronization: Option<usize>,
+    },
+    NeverSynchronized {
+        cycle: Cycle,
+    },
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 struct OctopusGroup<const N: usize>([[u8; N]; N]);
 
 impl<const N: usize> OctopusGroup<N> {
@@ -65,6 +126,27 @@ impl<const N: usize> OctopusGroup<N> {
         (0..n).map(|_| self.next_step()).sum()
     }
 
+    fn analyze_flashing(&self, limit: usize) -> Result<FlashingReport, FlashingError> {
+        let mut simulation = Simulation::new(*self);
+        let cycle = simulation.run_until_cycle(limit);
+        let first_synchronization = simulation
+            .history()
+            .iter()
+            .skip(1)
+            .position(|flashes| *flashes == N * N)
+            .map(|step| step + 1);
+        match cycle {
+            Some(cycle) => Ok(FlashingReport {
+                cycle,
+                first_synchronization,
+            }),
+            None => Err(FlashingError::StepLimitExceeded {
+                limit,
+                first_synchronization,
+            }),
+        }
+    }
+
     fn increase(&mut self, x: usize, y: usize) -> HashSet<(usize, usize)> {
         self.0[y][x] += 1;
         self.flash(x, y)
@@ -86,6 +168,22 @@ impl<const N: usize> OctopusGroup<N> {
     }
 }
 
+impl<const N: usize> CellularAutomaton for OctopusGroup<N> {
+    fn step(&self) -> Self {
+        let mut next = *self;
+        next.next_step();
+        next
+    }
+
+    fn population(&self) -> usize {
+        self.0
+            .iter()
+            .flatten()
+            .filter(|energy| **energy == 0)
+            .count()
+    }
+}
+
 impl<const N: usize> Display for OctopusGroup<N> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         for y in 0..N {
@@ -122,6 +220,10 @@ mod test {
 19991
 11111";
 
+    const NEVER_SYNCHRONIZED_SAMPLE: &str = r"317
+066
+907";
+
     const SAMPLE: &str = r"5483143223
 2745854711
 5264556173
@@ -224,10 +326,93 @@ mod test {
 
     #[test]
     fn first_synchronized_flashing_for_sample_should_be_at_step_195() {
-        let mut octopuses: OctopusGroup<10> = SAMPLE.parse().unwrap();
+        let octopuses: OctopusGroup<10> = SAMPLE.parse().unwrap();
+
+        let step = find_first_synchronized_flashing_step(&octopuses, STEP_LIMIT);
+
+        assert_eq!(step, Ok(195));
+    }
+
+    #[test]
+    fn analyze_flashing_should_find_sample_cycle_after_synchronization() {
+        let octopuses: OctopusGroup<10> = SAMPLE.parse().unwrap();
+
+        let report = octopuses.analyze_flashing(STEP_LIMIT);
+
+        assert_eq!(
+            report,
+            Ok(FlashingReport {
+                cycle: Cycle {
+                    start: 195,
+                    period: 10
+                },
+                first_synchronization: Some(195)
+            })
+        );
+    }
+
+    #[test]
+    fn analyze_flashing_should_report_cycles_that_never_synchronize() {
+        let octopuses: OctopusGroup<3> = NEVER_SYNCHRONIZED_SAMPLE.parse().unwrap();
+
+        let report = octopuses.analyze_flashing(STEP_LIMIT);
+
+        assert_eq!(
+            report,
+            Ok(FlashingReport {
+                cycle: Cycle {
+                    start: 55,
+                    period: 21
+                },
+                first_synchronization: None
+            })
+        );
+        assert_eq!(
+            find_first_synchronized_flashing_step(&octopuses, STEP_LIMIT),
+            Err(FlashingError::NeverSynchronized {
+                cycle: Cycle {
+                    start: 55,
+                    period: 21
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn analyze_flashing_should_stop_at_the_step_limit() {
+        let octopuses: OctopusGroup<3> = NEVER_SYNCHRONIZED_SAMPLE.parse().unwrap();
+
+        let report = octopuses.analyze_flashing(50);
+
+        assert_eq!(
+            report,
+            Err(FlashingError::StepLimitExceeded {
+                limit: 50,
+                first_synchronization: None
+            })
+        );
+    }
+
+    #[test]
+    fn first_synchronized_flashing_should_be_found_before_the_step_limit() {
+        let octopuses: OctopusGroup<10> = SAMPLE.parse().unwrap();
+
+        let step = find_first_synchronized_flashing_step(&octopuses, 200);
+
+        assert_eq!(step, Ok(195));
+    }
+
+    #[test]
+    fn simulation_should_record_synchronized_flashing_at_step_195() {
+        let octopuses: OctopusGroup<10> = SAMPLE.parse().unwrap();
+        let mut simulation = Simulation::new(octopuses);
 
-        let step = find_first_synchronized_flashing_step(&mut octopuses);
+        simulation.run_until_cycle(195);
 
-        assert_eq!(step, 195);
+        assert_eq!(simulation.history().iter().take(101).sum::<usize>(), 1656);
+        assert_eq!(simulation.history()[195], 100);
+        assert!(simulation.history()[..195]
+            .iter()
+            .all(|flashes| *flashes < 100));
     }
 }
@@ -1,9 +1,11 @@
 use itertools::Itertools;
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::iter::once;
 use std::str::FromStr;
 
-use super::{input, Puzzle};
+use super::{input, Options, Puzzle};
 
 lazy_static! {
     static ref INPUT: CaveMap = input::read_lines_from_file::<Edge>("day12").into();
@@ -19,18 +21,66 @@ impl Puzzle for Day12 {
     fn part_one(&self) -> String {
         format!(
             "Paths that visit small caves at most once: {}",
-            INPUT.compute_all_paths_visiting_small_caves_once().len()
+            INPUT.count_paths(&VisitPolicy::visiting_small_caves_once())
         )
     }
 
     fn part_two(&self) -> String {
         format!(
             "Paths that visit one small cave twice: {}",
-            INPUT
-                .compute_all_paths_visiting_one_small_cave_twice()
-                .len()
+            INPUT.count_paths(&VisitPolicy::visiting_one_small_cave_twice())
         )
     }
+
+    fn handle_options(&self, options: &Options) {
+        if options.has("paths") {
+            let policy = visit_policy_from_options(options);
+            println!(
+                "Paths matching the visit policy: {}",
+                INPUT.count_paths(&policy)
+            );
+        }
+
+        if let Some(file) = options.value("dot") {
+            let dot = match options.value("highlight") {
+                Some(index) => {
+                    let paths = INPUT.compute_all_paths_visiting_small_caves_once();
+                    match index.parse::<usize>().ok().and_then(|n| paths.get(n)) {
+                        Some(path) => INPUT.to_dot_highlighting(path),
+                        None => {
+                            println!(
+                                "Invalid path index {}, expected a number below {}",
+                                index,
+                                paths.len()
+                            );
+                            return;
+                        }
+                    }
+                }
+                None => INPUT.to_dot(),
+            };
+            std::fs::write(file, dot).unwrap();
+            println!("Cave map written to {}", file);
+        }
+    }
+}
+
+fn visit_policy_from_options(options: &Options) -> VisitPolicy {
+    let mut policy = VisitPolicy::default();
+    if let Some(count) = options.value("twice") {
+        policy =
+            policy.with_small_caves_visited_twice(count.parse().expect("Count should be a number"));
+    }
+    if let Some(cave) = options.value("forbid") {
+        policy = policy.forbidding(cave.parse().unwrap());
+    }
+    if let Some(cave) = options.value("through") {
+        policy = policy.passing_through(cave.parse().unwrap());
+    }
+    if let Some(max_length) = options.value("max-length") {
+        policy = policy.with_max_length(max_length.parse().expect("Max length should be a number"));
+    }
+    policy
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -45,6 +95,25 @@ impl Node {
     fn is_small_cave(&self) -> bool {
         matches!(self, Node::SmallCave(_))
     }
+
+    fn dot_shape(&self) -> &'static str {
+        match self {
+            Node::Start => "invhouse",
+            Node::End => "doublecircle",
+            Node::BigCave(_) => "box",
+            Node::SmallCave(_) => "circle",
+        }
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Node::Start => write!(f, "start"),
+            Node::End => write!(f, "end"),
+            Node::BigCave(name) | Node::SmallCave(name) => write!(f, "{}", name),
+        }
+    }
 }
 
 impl FromStr for Node {
@@ -85,24 +154,122 @@ impl FromStr for Edge {
     }
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+struct VisitPolicy {
+    small_caves_visited_twice: usize,
+    forbidden_caves: HashSet<Node>,
+    max_length: Option<usize>,
+    required_caves: HashSet<Node>,
+}
+
+impl VisitPolicy {
+    fn visiting_small_caves_once() -> Self {
+        Self::default()
+    }
+
+    fn visiting_one_small_cave_twice() -> Self {
+        Self::default().with_small_caves_visited_twice(1)
+    }
+
+    fn with_small_caves_visited_twice(mut self, count: usize) -> Self {
+        self.small_caves_visited_twice = count;
+        self
+    }
+
+    fn forbidding(mut self, cave: Node) -> Self {
+        self.forbidden_caves.insert(cave);
+        self
+    }
+
+    fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    fn passing_through(mut self, cave: Node) -> Self {
+        self.required_caves.insert(cave);
+        self
+    }
+
+    fn is_visitable(&self, node: &Node, current_path: &CurrentPath) -> bool {
+        if self.forbidden_caves.contains(node)
+            || self
+                .max_length
+                .is_some_and(|max_length| current_path.nodes.len() >= max_length)
+        {
+            return false;
+        }
+        if !node.is_small_cave() {
+            return true;
+        }
+        match current_path.visits(node) {
+            0 => true,
+            1 => current_path.small_caves_visited_twice < self.small_caves_visited_twice,
+            _ => false,
+        }
+    }
+
+    fn is_accepted(&self, path: &[&Node]) -> bool {
+        self.required_caves.iter().all(|cave| path.contains(&cave))
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+struct CurrentPath<'a> {
+    nodes: Vec<&'a Node>,
+    small_caves_visited_twice: usize,
+}
+
+impl<'a> CurrentPath<'a> {
+    fn visits(&self, node: &Node) -> usize {
+        self.nodes.iter().filter(|n| **n == node).count()
+    }
+
+    fn push(&mut self, node: &'a Node) {
+        if node.is_small_cave() && self.visits(node) == 1 {
+            self.small_caves_visited_twice += 1;
+        }
+        self.nodes.push(node);
+    }
+
+    fn pop(&mut self) {
+        if let Some(node) = self.nodes.pop() {
+            if node.is_small_cave() && self.visits(node) == 1 {
+                self.small_caves_visited_twice -= 1;
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 struct CaveMap(HashMap<Node, Vec<Node>>);
 
 impl CaveMap {
-    fn compute_all_paths(&self, is_visitable: fn(&Node, &[&Node]) -> bool) -> Vec<Vec<&Node>> {
+    fn compute_all_paths(&self, policy: &VisitPolicy) -> Vec<Vec<&Node>> {
         let mut paths = Vec::new();
+        self.walk_paths(policy, |path| paths.push(path.to_vec()));
+        paths
+    }
 
-        let mut current_path = Vec::new();
+    fn count_paths(&self, policy: &VisitPolicy) -> usize {
+        let mut count = 0;
+        self.walk_paths(policy, |_| count += 1);
+        count
+    }
+
+    fn walk_paths<'a>(&'a self, policy: &VisitPolicy, mut on_path: impl FnMut(&[&'a Node])) {
+        let mut current_path = CurrentPath::default();
         let mut to_visit = vec![vec![&Node::Start]];
         while let Some(next_nodes) = to_visit.last_mut() {
             if let Some(next) = next_nodes.pop() {
                 current_path.push(next);
-                if let Some(visitable_nodes) =
-                    self.find_next_visitable_nodes(&current_path, is_visitable)
+                if let Some(visitable_nodes) = self.find_next_visitable_nodes(&current_path, policy)
                 {
                     to_visit.push(visitable_nodes);
                 } else if next == &Node::End {
-                    paths.push(current_path.clone());
+                    if policy.is_accepted(&current_path.nodes) {
+                        on_path(&current_path.nodes);
+                    }
                     current_path.pop();
                 } else {
                     current_path.pop();
@@ -112,42 +279,87 @@ impl CaveMap {
                 to_visit.pop();
             }
         }
-
-        paths
     }
 
     fn find_next_visitable_nodes(
         &self,
-        current_path: &[&Node],
-        is_visitable: fn(&Node, &[&Node]) -> bool,
+        current_path: &CurrentPath,
+        policy: &VisitPolicy,
     ) -> Option<Vec<&Node>> {
         current_path
+            .nodes
             .last()
             .and_then(|last_node| self.0.get(*last_node))
             .map(|visitable_nodes| {
                 visitable_nodes
                     .iter()
-                    .filter(|n| is_visitable(n, current_path))
+                    .filter(|n| policy.is_visitable(n, current_path))
                     .collect::<Vec<_>>()
             })
             .filter(|visitable_nodes| !visitable_nodes.is_empty())
     }
 
     fn compute_all_paths_visiting_small_caves_once(&self) -> Vec<Vec<&Node>> {
-        self.compute_all_paths(|node, current_path| {
-            !node.is_small_cave() || !current_path.iter().contains(&node)
-        })
+        self.compute_all_paths(&VisitPolicy::visiting_small_caves_once())
     }
 
+    #[cfg(test)]
     fn compute_all_paths_visiting_one_small_cave_twice(&self) -> Vec<Vec<&Node>> {
-        self.compute_all_paths(|node, current_path| {
-            !node.is_small_cave()
-                || !current_path.iter().contains(&node)
-                || current_path
-                    .iter()
-                    .filter(|n| n.is_small_cave())
-                    .all_unique()
-        })
+        self.compute_all_paths(&VisitPolicy::visiting_one_small_cave_twice())
+    }
+
+    fn to_dot(&self) -> String {
+        self.to_dot_highlighting(&[])
+    }
+
+    fn to_dot_highlighting(&self, path: &[&Node]) -> String {
+        let highlighted_edges = path
+            .iter()
+            .tuple_windows()
+            .map(|(a, b)| edge_name(a, b))
+            .collect::<HashSet<_>>();
+        let nodes = self
+            .0
+            .iter()
+            .flat_map(|(from, to)| once(from).chain(to))
+            .unique()
+            .sorted_by_key(|node| node.to_string())
+            .map(|node| {
+                let color = if path.contains(&node) {
+                    ", color=red"
+                } else {
+                    ""
+                };
+                format!("    \"{}\" [shape={}{}];\n", node, node.dot_shape(), color)
+            });
+        let edges = self
+            .0
+            .iter()
+            .flat_map(|(from, to)| to.iter().map(move |to| edge_name(from, to)))
+            .unique()
+            .sorted()
+            .map(|(a, b)| {
+                let style = if highlighted_edges.contains(&(a.clone(), b.clone())) {
+                    " [color=red, penwidth=2]"
+                } else {
+                    ""
+                };
+                format!("    \"{}\" -- \"{}\"{};\n", a, b, style)
+            });
+        format!(
+            "graph caves {{\n{}{}}}\n",
+            nodes.collect::<String>(),
+            edges.collect::<String>()
+        )
+    }
+}
+
+fn edge_name(a: &Node, b: &Node) -> (String, String) {
+    let (a, b) = (a.to_string(), b.to_string());
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
     }
 }
 
@@ -286,4 +498,98 @@ start-RW
             3509
         );
     }
+
+    #[test]
+    fn count_paths_should_match_compute_all_paths_for_large_sample() {
+        let policy = VisitPolicy::visiting_one_small_cave_twice();
+
+        assert_eq!(
+            LARGE_SAMPLE.count_paths(&policy),
+            LARGE_SAMPLE.compute_all_paths(&policy).len()
+        );
+    }
+
+    #[test]
+    fn visiting_no_small_cave_twice_should_be_the_same_as_visiting_small_caves_once() {
+        let policy = VisitPolicy::default().with_small_caves_visited_twice(0);
+
+        assert_eq!(SMALL_SAMPLE.count_paths(&policy), 10);
+    }
+
+    #[test]
+    fn visiting_two_small_caves_twice_should_return_more_paths_than_one() {
+        let one = VisitPolicy::default().with_small_caves_visited_twice(1);
+        let two = VisitPolicy::default().with_small_caves_visited_twice(2);
+
+        assert!(SMALL_SAMPLE.count_paths(&two) > SMALL_SAMPLE.count_paths(&one));
+    }
+
+    #[test]
+    fn forbidding_a_cave_should_remove_all_paths_going_through_it() {
+        let b: Node = "b".parse().unwrap();
+        let policy = VisitPolicy::visiting_small_caves_once().forbidding(b.clone());
+
+        let paths = SMALL_SAMPLE.compute_all_paths(&policy);
+
+        assert_eq!(paths.len(), 2);
+        assert!(paths.iter().all(|path| !path.contains(&&b)));
+    }
+
+    #[test]
+    fn max_length_should_limit_the_number_of_nodes_in_paths() {
+        let policy = VisitPolicy::visiting_small_caves_once().with_max_length(4);
+
+        let paths = SMALL_SAMPLE.compute_all_paths(&policy);
+
+        assert_eq!(paths.len(), 4);
+        assert!(paths.iter().all(|path| path.len() <= 4));
+    }
+
+    #[test]
+    fn passing_through_a_cave_should_keep_only_paths_going_through_it() {
+        let c: Node = "c".parse().unwrap();
+        let policy = VisitPolicy::visiting_small_caves_once().passing_through(c.clone());
+
+        let paths = SMALL_SAMPLE.compute_all_paths(&policy);
+
+        assert_eq!(paths.len(), 5);
+        assert!(paths.iter().all(|path| path.contains(&&c)));
+    }
+
+    #[test]
+    fn to_dot_should_render_caves_with_distinct_shapes() {
+        assert_eq!(
+            SMALL_SAMPLE.to_dot(),
+            r#"graph caves {
+    "A" [shape=box];
+    "b" [shape=circle];
+    "c" [shape=circle];
+    "d" [shape=circle];
+    "end" [shape=doublecircle];
+    "start" [shape=invhouse];
+    "A" -- "b";
+    "A" -- "c";
+    "A" -- "end";
+    "A" -- "start";
+    "b" -- "d";
+    "b" -- "end";
+    "b" -- "start";
+}
+"#
+        );
+    }
+
+    #[test]
+    fn to_dot_highlighting_should_highlight_the_path() {
+        let path = ["start", "b", "A", "end"].map(|name| name.parse::<Node>().unwrap());
+        let path = path.iter().collect::<Vec<_>>();
+
+        let dot = SMALL_SAMPLE.to_dot_highlighting(&path);
+
+        assert!(dot.contains("    \"b\" [shape=circle, color=red];\n"));
+        assert!(dot.contains("    \"c\" [shape=circle];\n"));
+        assert!(dot.contains("    \"A\" -- \"b\" [color=red, penwidth=2];\n"));
+        assert!(dot.contains("    \"b\" -- \"start\" [color=red, penwidth=2];\n"));
+        assert!(dot.contains("    \"A\" -- \"c\";\n"));
+    }
 }
@@ -123,9 +123,8 @@ impl PairCount {
     }
 
     fn count_elements(&self, first_element: Option<char>) -> Result<ElementMinMax, ()> {
-        let mut all_count = first_element
-            .map(|c| [(c, 1)].into())
-            .unwrap_or_else(HashMap::new);
+        let mut all_count: HashMap<char, usize> =
+            first_element.map(|c| [(c, 1)].into()).unwrap_or_default();
 
         for ((_, c), n) in &self.0 {
             let count = all_count.entry(*c).or_insert(0);
@@ -1,7 +1,14 @@
+use itertools::Itertools;
 use lazy_static::lazy_static;
-use std::collections::VecDeque;
+#[cfg(feature = "bigint")]
+use num_bigint::BigUint;
+use std::cmp::min;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufReader, Bytes, ErrorKind, Read};
+use std::str::FromStr;
 
-use super::{input, Puzzle};
+use super::{input, Options, Puzzle};
 
 lazy_static! {
     static ref INPUT: String = input::read_file("day16");
@@ -23,7 +30,76 @@ impl Puzzle for Day16 {
     fn part_two(&self) -> String {
         let mut parser = PacketParser::new(&INPUT);
         let packet = parser.parse().unwrap();
-        format!("Result of evaluation: {}", packet.evaluate())
+        format!("Result of evaluation: {}", packet.evaluate().unwrap())
+    }
+
+    fn handle_options(&self, options: &Options) {
+        if let Some(length_type) = options.value("encode") {
+            let writer = match length_type {
+                "0" => PacketWriter::new(),
+                "1" => PacketWriter::with_length_type(|_| LengthType::SubPacketCount),
+                _ => panic!("Invalid length type: {}", length_type),
+            };
+            let packet = PacketParser::new(&INPUT).parse().unwrap();
+            match writer.write(&packet) {
+                Some(transmission) => println!("Re-encoded transmission: {}", transmission),
+                None => println!(
+                    "Packet {} cannot be encoded with length type {}",
+                    packet, length_type
+                ),
+            }
+        }
+        if let Some(expression) = options.value("expression") {
+            match expression.parse::<Packet>() {
+                Ok(packet) => {
+                    match PacketWriter::new().write(&packet) {
+                        Some(transmission) => println!("Transmission: {}", transmission),
+                        None => println!("Packet {} cannot be encoded", packet),
+                    }
+                    println!("Result of evaluation: {}", format_evaluation(&packet));
+                }
+                Err(error) => println!("{:?}", error),
+            }
+        }
+        if let Some(file) = options.value("decode-file") {
+            match File::open(file) {
+                Ok(file) => {
+                    let mut parser = if options.has("binary") {
+                        PacketParser::from_binary(file)
+                    } else {
+                        PacketParser::from_hex(file)
+                    };
+                    match parser.parse() {
+                        Ok(packet) => {
+                            println!("Expression: {}", packet);
+                            println!("Result of evaluation: {}", format_evaluation(&packet));
+                        }
+                        Err(error) => println!("{:?}", error),
+                    }
+                }
+                Err(error) => println!("Cannot open {}: {}", file, error),
+            }
+        }
+        if options.has("decode") {
+            let transmission = options.value("decode").unwrap_or(&INPUT);
+            match PacketParser::new(transmission).parse() {
+                Ok(packet) => println!("Expression: {}", packet),
+                Err(error) => println!("{:?}", error),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "bigint")]
+fn format_evaluation(packet: &Packet) -> String {
+    packet.evaluate_big().to_string()
+}
+
+#[cfg(not(feature = "bigint"))]
+fn format_evaluation(packet: &Packet) -> String {
+    match packet.evaluate() {
+        Ok(value) => value.to_string(),
+        Err(error) => format!("{:?}", error),
     }
 }
 
@@ -35,7 +111,7 @@ struct Packet {
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 enum PacketKind {
-    Literal(u64),
+    Literal(Literal),
     Sum(Vec<Packet>),
     Product(Vec<Packet>),
     Minimum(Vec<Packet>),
@@ -54,6 +130,98 @@ enum PacketKind {
     },
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+struct Literal(Vec<u8>);
+
+impl Literal {
+    fn from_groups(groups: impl IntoIterator<Item = u8>) -> Self {
+        Self(groups.into_iter().skip_while(|group| *group == 0).collect())
+    }
+
+    fn from_decimal(digits: &str) -> Self {
+        let mut groups = Vec::new();
+        for digit in digits.chars().filter_map(|c| c.to_digit(10)) {
+            let mut carry = digit;
+            for group in groups.iter_mut().rev() {
+                let value = *group as u32 * 10 + carry;
+                *group = (value & 0b1111) as u8;
+                carry = value >> 4;
+            }
+            while carry != 0 {
+                groups.insert(0, (carry & 0b1111) as u8);
+                carry >>= 4;
+            }
+        }
+        Self::from_groups(groups)
+    }
+
+    fn groups(&self) -> &[u8] {
+        if self.0.is_empty() {
+            &[0]
+        } else {
+            &self.0
+        }
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        (self.0.len() <= 16).then(|| {
+            self.0
+                .iter()
+                .fold(0, |number, group| (number << 4) | *group as u64)
+        })
+    }
+}
+
+impl From<u64> for Literal {
+    fn from(number: u64) -> Self {
+        Self::from_groups((0..16).rev().map(|i| ((number >> (i * 4)) & 0b1111) as u8))
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut groups = self.0.clone();
+        let mut digits = Vec::new();
+        while !groups.is_empty() {
+            let mut remainder = 0;
+            for group in groups.iter_mut() {
+                let value = remainder * 16 + *group;
+                *group = value / 10;
+                remainder = value % 10;
+            }
+            digits.push(char::from_digit(remainder as u32, 10).unwrap());
+            groups = Self::from_groups(groups).0;
+        }
+        if digits.is_empty() {
+            write!(f, "0")
+        } else {
+            write!(f, "{}", digits.iter().rev().collect::<String>())
+        }
+    }
+}
+
+impl PacketKind {
+    fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            PacketKind::GreaterThan { .. } | PacketKind::LessThan { .. } | PacketKind::Equal { .. }
+        )
+    }
+
+    fn type_id(&self) -> u8 {
+        match self {
+            PacketKind::Sum(_) => 0,
+            PacketKind::Product(_) => 1,
+            PacketKind::Minimum(_) => 2,
+            PacketKind::Maximum(_) => 3,
+            PacketKind::Literal(_) => 4,
+            PacketKind::GreaterThan { .. } => 5,
+            PacketKind::LessThan { .. } => 6,
+            PacketKind::Equal { .. } => 7,
+        }
+    }
+}
+
 impl Packet {
     fn sum_versions(&self) -> u32 {
         match &self.kind {
@@ -72,118 +240,444 @@ impl Packet {
         }
     }
 
-    fn evaluate(&self) -> u64 {
+    fn evaluate(&self) -> Result<u64, EvaluationError> {
         match &self.kind {
-            PacketKind::Literal(value) => *value,
-            PacketKind::Sum(sub_packets) => sub_packets.iter().map(Packet::evaluate).sum(),
-            PacketKind::Product(sub_packets) => sub_packets.iter().map(Packet::evaluate).product(),
-            PacketKind::Minimum(sub_packets) => {
-                sub_packets.iter().map(Packet::evaluate).min().unwrap_or(0)
+            PacketKind::Literal(value) => value.to_u64().ok_or(EvaluationError::LiteralTooWide),
+            PacketKind::Sum(sub_packets) => sub_packets.iter().try_fold(0u64, |sum, sub_packet| {
+                sum.checked_add(sub_packet.evaluate()?)
+                    .ok_or(EvaluationError::SumOverflow)
+            }),
+            PacketKind::Product(sub_packets) => {
+                sub_packets.iter().try_fold(1u64, |product, sub_packet| {
+                    product
+                        .checked_mul(sub_packet.evaluate()?)
+                        .ok_or(EvaluationError::ProductOverflow)
+                })
+            }
+            PacketKind::Minimum(sub_packets) => Ok(sub_packets
+                .iter()
+                .map(Packet::evaluate)
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .min()
+                .unwrap_or(0)),
+            PacketKind::Maximum(sub_packets) => Ok(sub_packets
+                .iter()
+                .map(Packet::evaluate)
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .max()
+                .unwrap_or(0)),
+            PacketKind::GreaterThan { first, second } => {
+                Ok((first.evaluate()? > second.evaluate()?) as u64)
+            }
+            PacketKind::LessThan { first, second } => {
+                Ok((first.evaluate()? < second.evaluate()?) as u64)
+            }
+            PacketKind::Equal { first, second } => {
+                Ok((first.evaluate()? == second.evaluate()?) as u64)
             }
-            PacketKind::Maximum(sub_packets) => {
-                sub_packets.iter().map(Packet::evaluate).max().unwrap_or(0)
+        }
+    }
+
+    #[cfg(feature = "bigint")]
+    fn evaluate_big(&self) -> BigUint {
+        match &self.kind {
+            PacketKind::Literal(value) => BigUint::from_radix_be(value.groups(), 16).unwrap(),
+            PacketKind::Sum(sub_packets) => sub_packets.iter().map(Packet::evaluate_big).sum(),
+            PacketKind::Product(sub_packets) => {
+                sub_packets.iter().map(Packet::evaluate_big).product()
             }
+            PacketKind::Minimum(sub_packets) => sub_packets
+                .iter()
+                .map(Packet::evaluate_big)
+                .min()
+                .unwrap_or_default(),
+            PacketKind::Maximum(sub_packets) => sub_packets
+                .iter()
+                .map(Packet::evaluate_big)
+                .max()
+                .unwrap_or_default(),
             PacketKind::GreaterThan { first, second } => {
-                if first.evaluate() > second.evaluate() {
-                    1
-                } else {
-                    0
-                }
+                BigUint::from((first.evaluate_big() > second.evaluate_big()) as u8)
             }
             PacketKind::LessThan { first, second } => {
-                if first.evaluate() < second.evaluate() {
-                    1
-                } else {
-                    0
-                }
+                BigUint::from((first.evaluate_big() < second.evaluate_big()) as u8)
             }
             PacketKind::Equal { first, second } => {
-                if first.evaluate() == second.evaluate() {
-                    1
-                } else {
-                    0
+                BigUint::from((first.evaluate_big() == second.evaluate_big()) as u8)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum EvaluationError {
+    LiteralTooWide,
+    SumOverflow,
+    ProductOverflow,
+}
+
+impl Display for Packet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            PacketKind::Literal(value) => write!(f, "{}", value),
+            PacketKind::Sum(sub_packets) => write_function(f, "sum", sub_packets),
+            PacketKind::Product(sub_packets) => write_function(f, "product", sub_packets),
+            PacketKind::Minimum(sub_packets) => write_function(f, "min", sub_packets),
+            PacketKind::Maximum(sub_packets) => write_function(f, "max", sub_packets),
+            PacketKind::GreaterThan { first, second } => write_comparison(f, first, ">", second),
+            PacketKind::LessThan { first, second } => write_comparison(f, first, "<", second),
+            PacketKind::Equal { first, second } => write_comparison(f, first, "==", second),
+        }
+    }
+}
+
+fn write_function(f: &mut Formatter<'_>, name: &str, arguments: &[Packet]) -> std::fmt::Result {
+    write!(f, "{}({})", name, arguments.iter().join(", "))
+}
+
+fn write_comparison(
+    f: &mut Formatter<'_>,
+    first: &Packet,
+    operator: &str,
+    second: &Packet,
+) -> std::fmt::Result {
+    write_comparison_operand(f, first)?;
+    write!(f, " {} ", operator)?;
+    write_comparison_operand(f, second)
+}
+
+fn write_comparison_operand(f: &mut Formatter<'_>, operand: &Packet) -> std::fmt::Result {
+    if operand.kind.is_comparison() {
+        write!(f, "({})", operand)
+    } else {
+        write!(f, "{}", operand)
+    }
+}
+
+impl FromStr for Packet {
+    type Err = ExpressionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ExpressionParser::new(s).parse()
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum ExpressionError {
+    UnexpectedChar {
+        column: usize,
+        expected: &'static str,
+        actual: char,
+    },
+    UnexpectedEnd {
+        expected: &'static str,
+    },
+    UnknownFunction {
+        column: usize,
+        name: String,
+    },
+}
+
+struct ExpressionParser {
+    source: Vec<char>,
+    position: usize,
+}
+
+impl ExpressionParser {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.chars().collect(),
+            position: 0,
+        }
+    }
+
+    pub fn parse(&mut self) -> Result<Packet, ExpressionError> {
+        let packet = self.parse_expression()?;
+        match self.peek() {
+            Some(c) => Err(self.unexpected_char("end of expression", c)),
+            None => Ok(packet),
+        }
+    }
+
+    fn parse_expression(&mut self) -> Result<Packet, ExpressionError> {
+        let first = Box::new(self.parse_operand()?);
+        let kind = match self.peek() {
+            Some('>') => {
+                self.position += 1;
+                PacketKind::GreaterThan {
+                    first,
+                    second: Box::new(self.parse_operand()?),
+                }
+            }
+            Some('<') => {
+                self.position += 1;
+                PacketKind::LessThan {
+                    first,
+                    second: Box::new(self.parse_operand()?),
+                }
+            }
+            Some('=') => {
+                self.position += 1;
+                self.expect('=', "'=='")?;
+                PacketKind::Equal {
+                    first,
+                    second: Box::new(self.parse_operand()?),
                 }
             }
+            _ => return Ok(*first),
+        };
+        Ok(Packet { version: 0, kind })
+    }
+
+    fn parse_operand(&mut self) -> Result<Packet, ExpressionError> {
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let packet = self.parse_expression()?;
+                self.expect(')', "')'")?;
+                Ok(packet)
+            }
+            Some(c) if c.is_ascii_digit() => self.parse_literal(),
+            Some(c) if c.is_ascii_alphabetic() => self.parse_function(),
+            Some(c) => Err(self.unexpected_char("operand", c)),
+            None => Err(ExpressionError::UnexpectedEnd {
+                expected: "operand",
+            }),
         }
     }
+
+    fn parse_literal(&mut self) -> Result<Packet, ExpressionError> {
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        Ok(Packet {
+            version: 0,
+            kind: PacketKind::Literal(Literal::from_decimal(&digits)),
+        })
+    }
+
+    fn parse_function(&mut self) -> Result<Packet, ExpressionError> {
+        let column = self.position + 1;
+        let name = self.take_while(|c| c.is_ascii_alphanumeric());
+        let kind: fn(Vec<Packet>) -> PacketKind = match name.as_str() {
+            "sum" => PacketKind::Sum,
+            "product" => PacketKind::Product,
+            "min" => PacketKind::Minimum,
+            "max" => PacketKind::Maximum,
+            _ => return Err(ExpressionError::UnknownFunction { column, name }),
+        };
+        self.expect('(', "'('")?;
+        let mut arguments = Vec::new();
+        if self.peek() == Some(')') {
+            self.position += 1;
+        } else {
+            loop {
+                arguments.push(self.parse_expression()?);
+                match self.peek() {
+                    Some(',') => self.position += 1,
+                    Some(')') => {
+                        self.position += 1;
+                        break;
+                    }
+                    Some(c) => return Err(self.unexpected_char("',' or ')'", c)),
+                    None => {
+                        return Err(ExpressionError::UnexpectedEnd {
+                            expected: "',' or ')'",
+                        })
+                    }
+                }
+            }
+        }
+        Ok(Packet {
+            version: 0,
+            kind: kind(arguments),
+        })
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        while self
+            .source
+            .get(self.position)
+            .is_some_and(|c| c.is_whitespace())
+        {
+            self.position += 1;
+        }
+        self.source.get(self.position).copied()
+    }
+
+    fn take_while(&mut self, predicate: fn(&char) -> bool) -> String {
+        let start = self.position;
+        while self.source.get(self.position).is_some_and(predicate) {
+            self.position += 1;
+        }
+        self.source[start..self.position].iter().collect()
+    }
+
+    fn expect(
+        &mut self,
+        expected_char: char,
+        expected: &'static str,
+    ) -> Result<(), ExpressionError> {
+        match self.peek() {
+            Some(c) if c == expected_char => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(c) => Err(self.unexpected_char(expected, c)),
+            None => Err(ExpressionError::UnexpectedEnd { expected }),
+        }
+    }
+
+    fn unexpected_char(&self, expected: &'static str, actual: char) -> ExpressionError {
+        ExpressionError::UnexpectedChar {
+            column: self.position + 1,
+            expected,
+            actual,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Field {
+    Version,
+    TypeId,
+    LiteralValue,
+    LengthTypeId,
+    SubPacketsLength,
+    SubPacketsCount,
+    Padding,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum DecodeError {
+    InvalidHex {
+        offset: usize,
+        field: Field,
+        actual: char,
+    },
+    UnexpectedEnd {
+        offset: usize,
+        field: Field,
+    },
+    TooManyBits {
+        offset: usize,
+        field: Field,
+        bits: u8,
+    },
+    WrongOperandCount {
+        offset: usize,
+        type_id: u8,
+        count: usize,
+    },
+    SubPacketsLengthMismatch {
+        offset: usize,
+        expected: usize,
+        actual: usize,
+    },
+    TrailingBits {
+        offset: usize,
+    },
+    Io {
+        offset: usize,
+        field: Field,
+        kind: ErrorKind,
+    },
 }
 
-struct PacketParser<'a> {
-    lexer: PacketLexer<'a>,
+struct PacketParser<R> {
+    lexer: PacketLexer<R>,
 }
 
-impl<'a> PacketParser<'a> {
+impl<'a> PacketParser<&'a [u8]> {
     pub fn new(source: &'a str) -> Self {
+        Self::from_hex(source.as_bytes())
+    }
+}
+
+impl<R: Read> PacketParser<R> {
+    pub fn from_hex(reader: R) -> Self {
+        Self {
+            lexer: PacketLexer::new(reader, Encoding::Hex),
+        }
+    }
+
+    pub fn from_binary(reader: R) -> Self {
         Self {
-            lexer: PacketLexer::new(source),
+            lexer: PacketLexer::new(reader, Encoding::Binary),
         }
     }
 
-    pub fn parse(&mut self) -> Option<Packet> {
-        self.parse_packet().map(|(packet, _)| packet)
+    pub fn parse(&mut self) -> Result<Packet, DecodeError> {
+        let (packet, _) = self.parse_packet()?;
+        self.parse_padding()?;
+        Ok(packet)
     }
 
-    fn parse_packet(&mut self) -> Option<(Packet, usize)> {
-        let version = self.lexer.read_number(3)? as u8;
-        let type_id = self.lexer.read_number(3)? as u8;
+    fn parse_packet(&mut self) -> Result<(Packet, usize), DecodeError> {
+        let offset = self.lexer.position();
+        let version = self.read_number(3, Field::Version)? as u8;
+        let type_id = self.read_number(3, Field::TypeId)? as u8;
 
         let (kind, size) = match type_id {
             4 => self.parse_literal()?,
-            _ => self.parse_operator(type_id)?,
+            _ => self.parse_operator(offset, type_id)?,
         };
 
-        Some((Packet { version, kind }, 6 + size))
+        Ok((Packet { version, kind }, 6 + size))
     }
 
-    fn parse_literal(&mut self) -> Option<(PacketKind, usize)> {
-        let (value, size) = self.lexer.read_grouped_number()?;
-        Some((PacketKind::Literal(value as u64), size))
+    fn parse_literal(&mut self) -> Result<(PacketKind, usize), DecodeError> {
+        let (value, size) = self
+            .lexer
+            .read_grouped_number()
+            .map_err(|error| error.in_field(Field::LiteralValue))?;
+        Ok((PacketKind::Literal(value), size))
     }
 
-    fn parse_operator(&mut self, operator_id: u8) -> Option<(PacketKind, usize)> {
-        let (mut sub_packets, size) = self.parse_sub_packets()?;
-        match operator_id {
-            0 => Some((PacketKind::Sum(sub_packets), size)),
-            1 => Some((PacketKind::Product(sub_packets), size)),
-            2 => Some((PacketKind::Minimum(sub_packets), size)),
-            3 => Some((PacketKind::Maximum(sub_packets), size)),
-            5 => Some((
-                PacketKind::GreaterThan {
-                    first: Box::new(sub_packets.remove(0)),
-                    second: Box::new(sub_packets.remove(0)),
-                },
-                size,
-            )),
-            6 => Some((
-                PacketKind::LessThan {
-                    first: Box::new(sub_packets.remove(0)),
-                    second: Box::new(sub_packets.remove(0)),
-                },
-                size,
-            )),
-            7 => Some((
-                PacketKind::Equal {
-                    first: Box::new(sub_packets.remove(0)),
-                    second: Box::new(sub_packets.remove(0)),
-                },
-                size,
-            )),
-            _ => None,
-        }
+    fn parse_operator(
+        &mut self,
+        offset: usize,
+        type_id: u8,
+    ) -> Result<(PacketKind, usize), DecodeError> {
+        let (sub_packets, size) = self.parse_sub_packets()?;
+        let kind = match type_id {
+            0 => PacketKind::Sum(sub_packets),
+            1 => PacketKind::Product(sub_packets),
+            2 => PacketKind::Minimum(sub_packets),
+            3 => PacketKind::Maximum(sub_packets),
+            _ => {
+                let [first, second] =
+                    <[Packet; 2]>::try_from(sub_packets).map_err(|sub_packets| {
+                        DecodeError::WrongOperandCount {
+                            offset,
+                            type_id,
+                            count: sub_packets.len(),
+                        }
+                    })?;
+                let (first, second) = (Box::new(first), Box::new(second));
+                match type_id {
+                    5 => PacketKind::GreaterThan { first, second },
+                    6 => PacketKind::LessThan { first, second },
+                    _ => PacketKind::Equal { first, second },
+                }
+            }
+        };
+        Ok((kind, size))
     }
 
-    fn parse_sub_packets(&mut self) -> Option<(Vec<Packet>, usize)> {
-        let length_type = self.lexer.read_number(1)?;
-        match length_type {
-            0 => self.parse_type_0_sub_packets(),
-            1 => self.parse_type_1_sub_packets(),
-            _ => None,
-        }
-        .map(|(content, size)| (content, size + 1))
+    fn parse_sub_packets(&mut self) -> Result<(Vec<Packet>, usize), DecodeError> {
+        let length_type = self.read_number(1, Field::LengthTypeId)?;
+        let (content, size) = if length_type == 0 {
+            self.parse_type_0_sub_packets()?
+        } else {
+            self.parse_type_1_sub_packets()?
+        };
+        Ok((content, size + 1))
     }
 
-    fn parse_type_0_sub_packets(&mut self) -> Option<(Vec<Packet>, usize)> {
-        let sub_packets_length = self.lexer.read_number(15)? as usize;
+    fn parse_type_0_sub_packets(&mut self) -> Result<(Vec<Packet>, usize), DecodeError> {
+        let sub_packets_length = self.read_number(15, Field::SubPacketsLength)? as usize;
+        let offset = self.lexer.position();
         let mut sub_packets = Vec::new();
         let mut current_length = 0;
         while current_length < sub_packets_length {
@@ -191,11 +685,18 @@ impl<'a> PacketParser<'a> {
             sub_packets.push(packet);
             current_length += size;
         }
-        Some((sub_packets, 15 + sub_packets_length))
+        if current_length != sub_packets_length {
+            return Err(DecodeError::SubPacketsLengthMismatch {
+                offset,
+                expected: sub_packets_length,
+                actual: current_length,
+            });
+        }
+        Ok((sub_packets, 15 + sub_packets_length))
     }
 
-    fn parse_type_1_sub_packets(&mut self) -> Option<(Vec<Packet>, usize)> {
-        let nb_packets = self.lexer.read_number(11)?;
+    fn parse_type_1_sub_packets(&mut self) -> Result<(Vec<Packet>, usize), DecodeError> {
+        let nb_packets = self.read_number(11, Field::SubPacketsCount)?;
         let mut sub_packets = Vec::new();
         let mut sub_packets_length = 0;
         for _ in 0..nb_packets {
@@ -203,73 +704,302 @@ impl<'a> PacketParser<'a> {
             sub_packets.push(packet);
             sub_packets_length += size;
         }
-        Some((sub_packets, 11 + sub_packets_length))
+        Ok((sub_packets, 11 + sub_packets_length))
+    }
+
+    fn parse_padding(&mut self) -> Result<(), DecodeError> {
+        while !self
+            .lexer
+            .is_at_end()
+            .map_err(|error| error.in_field(Field::Padding))?
+        {
+            let offset = self.lexer.position();
+            if self.read_number(1, Field::Padding)? != 0 {
+                return Err(DecodeError::TrailingBits { offset });
+            }
+        }
+        Ok(())
+    }
+
+    fn read_number(&mut self, bits: u8, field: Field) -> Result<u64, DecodeError> {
+        self.lexer
+            .read_number(bits)
+            .map_err(|error| error.in_field(field))
     }
 }
 
-struct PacketLexer<'a> {
-    source: &'a [u8],
-    next_byte: usize,
-    buffer: VecDeque<u8>,
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum LexerError {
+    InvalidHex { offset: usize, actual: char },
+    UnexpectedEnd { offset: usize },
+    TooManyBits { offset: usize, bits: u8 },
+    Io { offset: usize, kind: ErrorKind },
 }
 
-impl<'a> PacketLexer<'a> {
-    pub fn new(source: &'a str) -> Self {
+impl LexerError {
+    fn in_field(self, field: Field) -> DecodeError {
+        match self {
+            LexerError::InvalidHex { offset, actual } => DecodeError::InvalidHex {
+                offset,
+                field,
+                actual,
+            },
+            LexerError::UnexpectedEnd { offset } => DecodeError::UnexpectedEnd { offset, field },
+            LexerError::TooManyBits { offset, bits } => DecodeError::TooManyBits {
+                offset,
+                field,
+                bits,
+            },
+            LexerError::Io { offset, kind } => DecodeError::Io {
+                offset,
+                field,
+                kind,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Encoding {
+    Hex,
+    Binary,
+}
+
+impl Encoding {
+    fn bits_per_byte(&self) -> u32 {
+        match self {
+            Encoding::Hex => 4,
+            Encoding::Binary => 8,
+        }
+    }
+}
+
+struct PacketLexer<R> {
+    source: Bytes<BufReader<R>>,
+    encoding: Encoding,
+    buffer: u64,
+    buffered_bits: u32,
+    loaded_bits: usize,
+    pending_error: Option<LexerError>,
+}
+
+impl<R: Read> PacketLexer<R> {
+    pub fn new(reader: R, encoding: Encoding) -> Self {
         Self {
-            source: source.as_bytes(),
-            next_byte: 0,
-            buffer: VecDeque::new(),
+            source: BufReader::new(reader).bytes(),
+            encoding,
+            buffer: 0,
+            buffered_bits: 0,
+            loaded_bits: 0,
+            pending_error: None,
         }
     }
 
-    pub fn read_number(&mut self, bits: u8) -> Option<u64> {
-        let mut number = 0;
-        for _ in 0..bits {
-            number = number * 2 + self.next_bit()? as u64;
+    pub fn position(&self) -> usize {
+        self.loaded_bits - self.buffered_bits as usize
+    }
+
+    pub fn is_at_end(&mut self) -> Result<bool, LexerError> {
+        if self.buffered_bits == 0 {
+            self.fill_buffer()?;
         }
-        Some(number)
+        Ok(self.buffered_bits == 0)
     }
 
-    pub fn read_grouped_number(&mut self) -> Option<(u64, usize)> {
-        let mut prefix = self.next_bit()?;
-        let mut number = self.read_number(4)?;
-        let mut total_bits = 5;
+    pub fn read_number(&mut self, bits: u8) -> Result<u64, LexerError> {
+        if bits > 64 {
+            return Err(LexerError::TooManyBits {
+                offset: self.position(),
+                bits,
+            });
+        }
+        let mut number: u64 = 0;
+        let mut remaining_bits = bits as u32;
+        while remaining_bits > 0 {
+            if self.buffered_bits == 0 {
+                self.fill_buffer()?;
+                if self.buffered_bits == 0 {
+                    return Err(LexerError::UnexpectedEnd {
+                        offset: self.position(),
+                    });
+                }
+            }
+            let taken_bits = min(remaining_bits, self.buffered_bits);
+            self.buffered_bits -= taken_bits;
+            let chunk = (self.buffer >> self.buffered_bits) & (u64::MAX >> (64 - taken_bits));
+            number = number.checked_shl(taken_bits).unwrap_or(0) | chunk;
+            remaining_bits -= taken_bits;
+        }
+        Ok(number)
+    }
+
+    pub fn read_grouped_number(&mut self) -> Result<(Literal, usize), LexerError> {
+        let mut prefix = self.read_number(1)?;
+        let mut groups = vec![self.read_number(4)? as u8];
         while prefix != 0 {
-            prefix = self.next_bit()?;
-            number = (number << 4) + self.read_number(4)?;
-            total_bits += 5;
+            prefix = self.read_number(1)?;
+            groups.push(self.read_number(4)? as u8);
+        }
+        let total_bits = groups.len() * 5;
+        Ok((Literal::from_groups(groups), total_bits))
+    }
+
+    fn fill_buffer(&mut self) -> Result<(), LexerError> {
+        if let Some(error) = self.pending_error.take() {
+            return Err(error);
         }
-        Some((number, total_bits))
+        let bits_per_byte = self.encoding.bits_per_byte();
+        while self.buffered_bits + bits_per_byte <= u64::BITS {
+            match self.next_chunk() {
+                Ok(Some(bits)) => {
+                    self.buffer = (self.buffer << bits_per_byte) | bits;
+                    self.buffered_bits += bits_per_byte;
+                    self.loaded_bits += bits_per_byte as usize;
+                }
+                Ok(None) => break,
+                Err(error) if self.buffered_bits == 0 => return Err(error),
+                Err(error) => {
+                    self.pending_error = Some(error);
+                    break;
+                }
+            }
+        }
+        Ok(())
     }
 
-    pub fn next_bit(&mut self) -> Option<u8> {
-        if self.buffer.is_empty() {
-            self.fill_buffer();
+    fn next_chunk(&mut self) -> Result<Option<u64>, LexerError> {
+        loop {
+            let byte = match self.source.next() {
+                Some(Ok(byte)) => byte,
+                Some(Err(error)) => {
+                    return Err(LexerError::Io {
+                        offset: self.loaded_bits,
+                        kind: error.kind(),
+                    })
+                }
+                None => return Ok(None),
+            };
+            return match self.encoding {
+                Encoding::Hex if byte.is_ascii_whitespace() => continue,
+                Encoding::Hex => (byte as char)
+                    .to_digit(16)
+                    .map(|bits| Some(bits as u64))
+                    .ok_or(LexerError::InvalidHex {
+                        offset: self.loaded_bits,
+                        actual: byte as char,
+                    }),
+                Encoding::Binary => Ok(Some(byte as u64)),
+            };
         }
-        self.buffer.pop_front()
     }
+}
 
-    fn fill_buffer(&mut self) {
-        for _ in 0..4 {
-            if let Some(n) = self.source.get(self.next_byte) {
-                let bits = (*n as char).to_digit(16).unwrap() as u8;
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum LengthType {
+    TotalLength,
+    SubPacketCount,
+}
+
+struct PacketWriter {
+    bits: Vec<u8>,
+    length_type: fn(&Packet) -> LengthType,
+}
 
-                self.buffer.push_back((bits & 0b1000) >> 3);
-                self.buffer.push_back((bits & 0b0100) >> 2);
-                self.buffer.push_back((bits & 0b0010) >> 1);
-                self.buffer.push_back(bits & 0b0001);
+impl PacketWriter {
+    pub fn new() -> Self {
+        Self::with_length_type(|_| LengthType::TotalLength)
+    }
 
-                self.next_byte += 1;
-            } else {
-                break;
+    pub fn with_length_type(length_type: fn(&Packet) -> LengthType) -> Self {
+        Self {
+            bits: Vec::new(),
+            length_type,
+        }
+    }
+
+    pub fn write(mut self, packet: &Packet) -> Option<String> {
+        self.write_packet(packet)?;
+        Some(
+            self.bits
+                .chunks(4)
+                .map(|chunk| {
+                    let digit = (0..4).fold(0, |digit, i| digit * 2 + chunk.get(i).unwrap_or(&0));
+                    char::from_digit(digit as u32, 16)
+                        .unwrap()
+                        .to_ascii_uppercase()
+                })
+                .collect(),
+        )
+    }
+
+    fn write_packet(&mut self, packet: &Packet) -> Option<()> {
+        self.write_number(packet.version as u64, 3)?;
+        self.write_number(packet.kind.type_id() as u64, 3)?;
+        match &packet.kind {
+            PacketKind::Literal(value) => self.write_grouped_number(value),
+            PacketKind::Sum(sub_packets)
+            | PacketKind::Product(sub_packets)
+            | PacketKind::Minimum(sub_packets)
+            | PacketKind::Maximum(sub_packets) => {
+                self.write_sub_packets(packet, sub_packets.iter())
+            }
+            PacketKind::GreaterThan { first, second }
+            | PacketKind::LessThan { first, second }
+            | PacketKind::Equal { first, second } => {
+                self.write_sub_packets(packet, [first.as_ref(), second.as_ref()].into_iter())
             }
         }
     }
+
+    fn write_sub_packets<'a>(
+        &mut self,
+        packet: &Packet,
+        sub_packets: impl ExactSizeIterator<Item = &'a Packet>,
+    ) -> Option<()> {
+        match (self.length_type)(packet) {
+            LengthType::TotalLength => {
+                let mut sub_packets_writer = Self::with_length_type(self.length_type);
+                for sub_packet in sub_packets {
+                    sub_packets_writer.write_packet(sub_packet)?;
+                }
+                self.write_number(0, 1)?;
+                self.write_number(sub_packets_writer.bits.len() as u64, 15)?;
+                self.bits.extend(sub_packets_writer.bits);
+            }
+            LengthType::SubPacketCount => {
+                self.write_number(1, 1)?;
+                self.write_number(sub_packets.len() as u64, 11)?;
+                for sub_packet in sub_packets {
+                    self.write_packet(sub_packet)?;
+                }
+            }
+        }
+        Some(())
+    }
+
+    fn write_number(&mut self, number: u64, bits: u8) -> Option<()> {
+        if bits < 64 && number >> bits != 0 {
+            return None;
+        }
+        self.bits
+            .extend((0..bits).rev().map(|i| ((number >> i) & 1) as u8));
+        Some(())
+    }
+
+    fn write_grouped_number(&mut self, number: &Literal) -> Option<()> {
+        let groups = number.groups();
+        for (i, group) in groups.iter().enumerate() {
+            self.write_number((i + 1 < groups.len()) as u64, 1)?;
+            self.write_number(*group as u64, 4)?;
+        }
+        Some(())
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn packet_parser_should_parse_a_literal_packet() {
@@ -279,9 +1009,9 @@ mod test {
 
         assert_eq!(
             result,
-            Some(Packet {
+            Ok(Packet {
                 version: 6,
-                kind: PacketKind::Literal(2021),
+                kind: PacketKind::Literal(2021.into()),
             }),
         );
     }
@@ -299,11 +1029,11 @@ mod test {
                 kind: PacketKind::LessThan {
                     first: Box::new(Packet {
                         version: 6,
-                        kind: PacketKind::Literal(10),
+                        kind: PacketKind::Literal(10.into()),
                     }),
                     second: Box::new(Packet {
                         version: 2,
-                        kind: PacketKind::Literal(20),
+                        kind: PacketKind::Literal(20.into()),
                     }),
                 },
             },
@@ -323,15 +1053,15 @@ mod test {
                 kind: PacketKind::Maximum(vec![
                     Packet {
                         version: 2,
-                        kind: PacketKind::Literal(1)
+                        kind: PacketKind::Literal(1.into())
                     },
                     Packet {
                         version: 4,
-                        kind: PacketKind::Literal(2)
+                        kind: PacketKind::Literal(2.into())
                     },
                     Packet {
                         version: 1,
-                        kind: PacketKind::Literal(3)
+                        kind: PacketKind::Literal(3.into())
                     },
                 ],),
             },
@@ -373,4 +1103,419 @@ mod test {
 
         assert_eq!(packet.sum_versions(), 31);
     }
+
+    #[test]
+    fn packet_writer_should_write_a_literal_packet() {
+        let packet = Packet {
+            version: 6,
+            kind: PacketKind::Literal(2021.into()),
+        };
+
+        let result = PacketWriter::new().write(&packet);
+
+        assert_eq!(result, Some("D2FE28".to_string()));
+    }
+
+    #[test]
+    fn packet_writer_should_write_a_type_0_operator() {
+        let packet = PacketParser::new("38006F45291200").parse().unwrap();
+
+        let result = PacketWriter::new().write(&packet);
+
+        assert_eq!(result, Some("38006F4529120".to_string()));
+    }
+
+    #[test]
+    fn packet_writer_should_write_a_type_1_operator() {
+        let packet = PacketParser::new("EE00D40C823060").parse().unwrap();
+
+        let result = PacketWriter::with_length_type(|_| LengthType::SubPacketCount).write(&packet);
+
+        assert_eq!(result, Some("EE00D40C82306".to_string()));
+    }
+
+    #[test]
+    fn packet_writer_should_fail_on_version_wider_than_3_bits() {
+        let packet = Packet {
+            version: 8,
+            kind: PacketKind::Literal(1.into()),
+        };
+
+        let result = PacketWriter::new().write(&packet);
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn expression_should_be_parsed_into_a_packet() {
+        let packet: Packet = "sum(1, product(2, 3), min(4, 5)) > 7".parse().unwrap();
+
+        assert_eq!(
+            packet,
+            Packet {
+                version: 0,
+                kind: PacketKind::GreaterThan {
+                    first: Box::new(Packet {
+                        version: 0,
+                        kind: PacketKind::Sum(vec![
+                            Packet {
+                                version: 0,
+                                kind: PacketKind::Literal(1.into()),
+                            },
+                            Packet {
+                                version: 0,
+                                kind: PacketKind::Product(vec![
+                                    Packet {
+                                        version: 0,
+                                        kind: PacketKind::Literal(2.into()),
+                                    },
+                                    Packet {
+                                        version: 0,
+                                        kind: PacketKind::Literal(3.into()),
+                                    },
+                                ]),
+                            },
+                            Packet {
+                                version: 0,
+                                kind: PacketKind::Minimum(vec![
+                                    Packet {
+                                        version: 0,
+                                        kind: PacketKind::Literal(4.into()),
+                                    },
+                                    Packet {
+                                        version: 0,
+                                        kind: PacketKind::Literal(5.into()),
+                                    },
+                                ]),
+                            },
+                        ]),
+                    }),
+                    second: Box::new(Packet {
+                        version: 0,
+                        kind: PacketKind::Literal(7.into()),
+                    }),
+                },
+            },
+        );
+        assert_eq!(packet.evaluate(), Ok(1));
+    }
+
+    #[test]
+    fn decoded_packet_should_be_displayed_as_an_expression() {
+        let packet = PacketParser::new("9C0141080250320F1802104A08")
+            .parse()
+            .unwrap();
+
+        assert_eq!(packet.to_string(), "sum(1, 3) == product(2, 2)");
+    }
+
+    #[test]
+    fn nested_comparisons_should_be_displayed_with_parentheses() {
+        let packet: Packet = "(1 < 2)==max( 1 ,0 )".parse().unwrap();
+
+        assert_eq!(packet.to_string(), "(1 < 2) == max(1, 0)");
+    }
+
+    #[test]
+    fn expression_parser_should_report_unknown_functions() {
+        let result = "sum(1, avg(2))".parse::<Packet>();
+
+        assert_eq!(
+            result,
+            Err(ExpressionError::UnknownFunction {
+                column: 8,
+                name: "avg".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn expression_parser_should_report_unexpected_chars() {
+        let result = "sum(1; 2)".parse::<Packet>();
+
+        assert_eq!(
+            result,
+            Err(ExpressionError::UnexpectedChar {
+                column: 6,
+                expected: "',' or ')'",
+                actual: ';',
+            })
+        );
+    }
+
+    #[test]
+    fn expression_parser_should_report_unexpected_end() {
+        let result = "max(1, 2) <".parse::<Packet>();
+
+        assert_eq!(
+            result,
+            Err(ExpressionError::UnexpectedEnd {
+                expected: "operand"
+            })
+        );
+    }
+
+    #[test]
+    fn packet_parser_should_report_invalid_hex_characters() {
+        let mut parser = PacketParser::new("D2XE28");
+
+        let result = parser.parse();
+
+        assert_eq!(
+            result,
+            Err(DecodeError::InvalidHex {
+                offset: 8,
+                field: Field::LiteralValue,
+                actual: 'X',
+            })
+        );
+    }
+
+    #[test]
+    fn packet_parser_should_report_truncated_input() {
+        let mut parser = PacketParser::new("38006F4529");
+
+        let result = parser.parse();
+
+        assert_eq!(
+            result,
+            Err(DecodeError::UnexpectedEnd {
+                offset: 40,
+                field: Field::LiteralValue,
+            })
+        );
+    }
+
+    #[test]
+    fn packet_parser_should_report_comparisons_without_two_operands() {
+        let mut parser = PacketParser::new("DA004408");
+
+        let result = parser.parse();
+
+        assert_eq!(
+            result,
+            Err(DecodeError::WrongOperandCount {
+                offset: 0,
+                type_id: 6,
+                count: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn packet_parser_should_report_sub_packets_overrunning_their_length() {
+        let mut parser = PacketParser::new("0000284080");
+
+        let result = parser.parse();
+
+        assert_eq!(
+            result,
+            Err(DecodeError::SubPacketsLengthMismatch {
+                offset: 22,
+                expected: 10,
+                actual: 11,
+            })
+        );
+    }
+
+    #[test]
+    fn packet_parser_should_report_trailing_non_zero_bits() {
+        let mut parser = PacketParser::new("D2FE2801");
+
+        let result = parser.parse();
+
+        assert_eq!(result, Err(DecodeError::TrailingBits { offset: 31 }));
+    }
+
+    #[test]
+    fn packet_lexer_should_read_full_64_bit_numbers() {
+        let mut lexer = PacketLexer::new("0123456789ABCDEF".as_bytes(), Encoding::Hex);
+
+        assert_eq!(lexer.read_number(64), Ok(0x0123456789ABCDEF));
+    }
+
+    #[test]
+    fn packet_lexer_should_reject_numbers_wider_than_64_bits() {
+        let mut lexer = PacketLexer::new("0123456789ABCDEF0".as_bytes(), Encoding::Hex);
+
+        assert_eq!(
+            lexer.read_number(65),
+            Err(LexerError::TooManyBits {
+                offset: 0,
+                bits: 65
+            })
+        );
+    }
+
+    #[test]
+    fn packet_lexer_should_read_numbers_across_chunks() {
+        let mut lexer =
+            PacketLexer::new("0123456789ABCDEF0123456789ABCDEF".as_bytes(), Encoding::Hex);
+
+        assert_eq!(lexer.read_number(4), Ok(0x0));
+        assert_eq!(lexer.read_number(64), Ok(0x123456789ABCDEF0));
+        assert_eq!(lexer.read_number(12), Ok(0x123));
+        assert_eq!(lexer.position(), 80);
+    }
+
+    #[test]
+    fn packet_lexer_should_read_grouped_numbers() {
+        let mut lexer = PacketLexer::new("D2FE28".as_bytes(), Encoding::Hex);
+
+        assert_eq!(lexer.read_number(6), Ok(0b110100));
+        assert_eq!(lexer.read_grouped_number(), Ok((2021.into(), 15)));
+        assert_eq!(lexer.read_number(3), Ok(0));
+        assert_eq!(lexer.is_at_end(), Ok(true));
+    }
+
+    #[test]
+    fn packet_lexer_should_skip_whitespace_in_hex_text() {
+        let mut lexer = PacketLexer::new("D2\nFE 28\n".as_bytes(), Encoding::Hex);
+
+        assert_eq!(lexer.read_number(24), Ok(0xD2FE28));
+        assert_eq!(lexer.is_at_end(), Ok(true));
+    }
+
+    #[test]
+    fn packet_parser_should_parse_raw_binary_bytes() {
+        let mut parser =
+            PacketParser::from_binary([0x38, 0x00, 0x6F, 0x45, 0x29, 0x12, 0x00].as_slice());
+
+        let result = parser.parse();
+
+        assert_eq!(result, PacketParser::new("38006F45291200").parse());
+    }
+
+    #[test]
+    fn packet_parser_should_read_from_any_reader() {
+        let reader = "A0016C8801620"
+            .as_bytes()
+            .chain("17C3686B18A3D4780".as_bytes());
+        let mut parser = PacketParser::from_hex(reader);
+
+        let packet = parser.parse().unwrap();
+
+        assert_eq!(packet.sum_versions(), 31);
+    }
+
+    #[test]
+    fn packet_parser_should_decode_literals_wider_than_64_bits() {
+        let packet: Packet = "340282366920938463463374607431768211456".parse().unwrap();
+        let encoded = PacketWriter::new().write(&packet).unwrap();
+
+        let decoded = PacketParser::new(&encoded).parse().unwrap();
+
+        assert_eq!(
+            decoded.to_string(),
+            "340282366920938463463374607431768211456"
+        );
+        assert_eq!(decoded.evaluate(), Err(EvaluationError::LiteralTooWide));
+    }
+
+    #[test]
+    fn evaluate_should_report_sum_overflow() {
+        let packet: Packet = "sum(18446744073709551615, 1)".parse().unwrap();
+
+        assert_eq!(packet.evaluate(), Err(EvaluationError::SumOverflow));
+    }
+
+    #[test]
+    fn evaluate_should_report_product_overflow() {
+        let packet: Packet = "product(4294967296, 4294967296)".parse().unwrap();
+
+        assert_eq!(packet.evaluate(), Err(EvaluationError::ProductOverflow));
+    }
+
+    #[test]
+    fn evaluate_should_not_overflow_on_the_largest_value() {
+        let packet: Packet = "sum(18446744073709551614, 1) == 18446744073709551615"
+            .parse()
+            .unwrap();
+
+        assert_eq!(packet.evaluate(), Ok(1));
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn evaluate_big_should_compute_values_wider_than_64_bits() {
+        let packet: Packet = "product(4294967296, 4294967296, 3)".parse().unwrap();
+
+        assert_eq!(
+            packet.evaluate_big().to_string(),
+            "55340232221128654848".to_string()
+        );
+    }
+
+    fn arbitrary_packet() -> impl Strategy<Value = Packet> {
+        let value = prop_oneof![
+            any::<u64>().prop_map(Literal::from),
+            prop::collection::vec(0..16u8, 1..40).prop_map(Literal::from_groups),
+        ];
+        let literal = (0..8u8, value).prop_map(|(version, value)| Packet {
+            version,
+            kind: PacketKind::Literal(value),
+        });
+        literal.prop_recursive(4, 32, 4, |inner| {
+            let operands = prop::collection::vec(inner.clone(), 0..4);
+            let comparison = (0..3u8, inner.clone(), inner).prop_map(|(kind, first, second)| {
+                let (first, second) = (Box::new(first), Box::new(second));
+                match kind {
+                    0 => PacketKind::GreaterThan { first, second },
+                    1 => PacketKind::LessThan { first, second },
+                    _ => PacketKind::Equal { first, second },
+                }
+            });
+            let operator = (0..4u8, operands).prop_map(|(kind, sub_packets)| match kind {
+                0 => PacketKind::Sum(sub_packets),
+                1 => PacketKind::Product(sub_packets),
+                2 => PacketKind::Minimum(sub_packets),
+                _ => PacketKind::Maximum(sub_packets),
+            });
+            (0..8u8, prop_oneof![operator, comparison])
+                .prop_map(|(version, kind)| Packet { version, kind })
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn parse_should_invert_write_with_total_length(packet in arbitrary_packet()) {
+            let encoded = PacketWriter::new().write(&packet).unwrap();
+
+            prop_assert_eq!(PacketParser::new(&encoded).parse(), Ok(packet));
+        }
+
+        #[test]
+        fn parse_should_invert_write_with_sub_packet_count(packet in arbitrary_packet()) {
+            let encoded = PacketWriter::with_length_type(|_| LengthType::SubPacketCount)
+                .write(&packet)
+                .unwrap();
+
+            prop_assert_eq!(PacketParser::new(&encoded).parse(), Ok(packet));
+        }
+
+        #[test]
+        fn parse_should_invert_write_with_mixed_length_types(packet in arbitrary_packet()) {
+            let encoded = PacketWriter::with_length_type(|packet| {
+                if packet.version % 2 == 0 {
+                    LengthType::TotalLength
+                } else {
+                    LengthType::SubPacketCount
+                }
+            })
+            .write(&packet)
+            .unwrap();
+
+            prop_assert_eq!(PacketParser::new(&encoded).parse(), Ok(packet));
+        }
+
+        #[test]
+        fn displayed_packet_should_parse_back_to_the_same_expression(packet in arbitrary_packet()) {
+            let expression = packet.to_string();
+
+            let parsed: Packet = expression.parse().unwrap();
+
+            prop_assert_eq!(parsed.to_string(), expression);
+            prop_assert_eq!(parsed.evaluate(), packet.evaluate());
+        }
+    }
 }
@@ -1,11 +1,11 @@
-use itertools::Itertools;
 use lazy_static::lazy_static;
+use rayon::prelude::*;
 use std::collections::VecDeque;
 use std::fmt::{Display, Formatter};
 use std::ops::Add;
 use std::str::FromStr;
 
-use super::{input, Puzzle};
+use super::{input, Options, Puzzle};
 
 lazy_static! {
     static ref INPUT: Vec<Number> = input::read_lines_from_file("day18");
@@ -24,14 +24,32 @@ impl Puzzle for Day18 {
     }
 
     fn part_two(&self) -> String {
-        let result = INPUT
-            .iter()
-            .cartesian_product(INPUT.iter())
-            .map(|(n1, n2)| n1.clone() + n2.clone())
-            .map(|n| n.magnitude())
-            .max()
-            .unwrap();
-        format!("Maximum magnitude of sum of two numbers: {}", result)
+        let numbers = INPUT.iter().map(FlatNumber::from).collect::<Vec<_>>();
+        let (_, _, result) = best_pair(
+            &numbers,
+            |n1, n2| n1.clone() + n2.clone(),
+            FlatNumber::magnitude,
+        )
+        .unwrap();
+        format!(
+            "Maximum magnitude of sum of two numbers: {}",
+            result.magnitude()
+        )
+    }
+
+    fn handle_options(&self, options: &Options) {
+        if options.has("trace") {
+            INPUT.iter().cloned().reduce(|n1, n2| {
+                let number = Number::pair(n1, n2);
+                println!("after addition: {}", number);
+                number
+                    .reduce_steps()
+                    .inspect(|(action, number)| println!("after {}: {}", action, number))
+                    .last()
+                    .map(|(_, number)| number)
+                    .unwrap_or(number)
+            });
+        }
     }
 }
 
@@ -39,12 +57,58 @@ fn sum_numbers(numbers: Vec<Number>) -> Number {
     numbers.into_iter().reduce(|n1, n2| n1 + n2).unwrap()
 }
 
+fn best_pair<T, R, K>(
+    items: &[T],
+    operation: impl Fn(&T, &T) -> R + Sync,
+    key: impl Fn(&R) -> K + Sync,
+) -> Option<(usize, usize, R)>
+where
+    T: Sync,
+    R: Send,
+    K: Ord + Send,
+{
+    (0..items.len())
+        .into_par_iter()
+        .flat_map_iter(|i| {
+            (0..items.len())
+                .filter(move |j| *j != i)
+                .map(move |j| (i, j))
+        })
+        .map(|(i, j)| {
+            let result = operation(&items[i], &items[j]);
+            (key(&result), i, j, result)
+        })
+        .max_by(|(key1, i1, j1, _), (key2, i2, j2, _)| key1.cmp(key2).then((i2, j2).cmp(&(i1, j1))))
+        .map(|(_, i, j, result)| (i, j, result))
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 enum Number {
     Value(u32),
     Pair(Box<Number>, Box<Number>),
 }
 
+type Explosion<'a> = (
+    Vec<Direction>,
+    Option<&'a mut u32>,
+    &'a mut Number,
+    Option<&'a mut u32>,
+);
+
+fn current_path(to_visit: &[VecDeque<&mut Number>]) -> Vec<Direction> {
+    to_visit
+        .iter()
+        .skip(1)
+        .map(|numbers| {
+            if numbers.is_empty() {
+                Direction::Right
+            } else {
+                Direction::Left
+            }
+        })
+        .collect()
+}
+
 impl Number {
     pub fn pair(left: impl Into<Number>, right: impl Into<Number>) -> Self {
         Self::Pair(Box::new(left.into()), Box::new(right.into()))
@@ -58,33 +122,43 @@ impl Number {
     }
 
     pub fn reduce(&mut self) {
-        loop {
-            if let Some((left_value, number, right_value)) = self.find_pair_to_explode() {
-                number.explode(left_value, right_value);
-            } else if let Some(number) = self.find_number_to_split() {
-                number.split();
-            } else {
-                break;
-            }
+        while self.reduce_step().is_some() {}
+    }
+
+    pub fn reduce_steps(&self) -> ReduceSteps {
+        ReduceSteps(self.clone())
+    }
+
+    fn reduce_step(&mut self) -> Option<ReduceAction> {
+        if let Some((path, left_value, number, right_value)) = self.find_pair_to_explode() {
+            number.explode(left_value, right_value);
+            Some(ReduceAction::Explode(path))
+        } else if let Some((path, number)) = self.find_number_to_split() {
+            number.split();
+            Some(ReduceAction::Split(path))
+        } else {
+            None
         }
     }
 
-    fn find_pair_to_explode(
-        &mut self,
-    ) -> Option<(Option<&mut u32>, &mut Number, Option<&mut u32>)> {
+    fn find_pair_to_explode(&mut self) -> Option<Explosion<'_>> {
         let mut to_visit = vec![VecDeque::from([self])];
         let mut last_value = None;
         let mut result = None;
         while let Some(numbers) = to_visit.last_mut() {
             if let Some(number) = numbers.pop_front() {
                 if let Self::Value(value) = number {
-                    if let Some((left, to_explode)) = result {
-                        return Some((left, to_explode, Some(value)));
+                    if let Some((path, left, to_explode)) = result {
+                        return Some((path, left, to_explode, Some(value)));
                     } else {
                         last_value.replace(value);
                     }
                 } else if to_visit.len() == 5 && result.is_none() {
-                    result = Some((std::mem::take(&mut last_value), number))
+                    result = Some((
+                        current_path(&to_visit),
+                        std::mem::take(&mut last_value),
+                        number,
+                    ))
                 } else if let Self::Pair(left, right) = number {
                     to_visit.push(VecDeque::from([left.as_mut(), right.as_mut()]));
                 }
@@ -92,17 +166,17 @@ impl Number {
                 to_visit.pop();
             }
         }
-        result.map(|(left, to_explode)| (left, to_explode, None))
+        result.map(|(path, left, to_explode)| (path, left, to_explode, None))
     }
 
-    fn find_number_to_split(&mut self) -> Option<&mut Number> {
+    fn find_number_to_split(&mut self) -> Option<(Vec<Direction>, &mut Number)> {
         let mut to_visit = vec![VecDeque::from([self])];
         while let Some(numbers) = to_visit.last_mut() {
             if let Some(number) = numbers.pop_front() {
                 match number {
                     Self::Value(value) => {
                         if *value >= 10 {
-                            return Some(number);
+                            return Some((current_path(&to_visit), number));
                         }
                     }
                     Self::Pair(left, right) => {
@@ -137,6 +211,46 @@ impl Number {
     }
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Direction {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum ReduceAction {
+    Explode(Vec<Direction>),
+    Split(Vec<Direction>),
+}
+
+impl Display for ReduceAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (name, path) = match self {
+            Self::Explode(path) => ("explode", path),
+            Self::Split(path) => ("split", path),
+        };
+        write!(f, "{} at ", name)?;
+        for direction in path {
+            match direction {
+                Direction::Left => write!(f, "L")?,
+                Direction::Right => write!(f, "R")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct ReduceSteps(Number);
+
+impl Iterator for ReduceSteps {
+    type Item = (ReduceAction, Number);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let action = self.0.reduce_step()?;
+        Some((action, self.0.clone()))
+    }
+}
+
 impl Add for Number {
     type Output = Number;
 
@@ -153,40 +267,253 @@ impl From<u32> for Number {
     }
 }
 
-impl From<Vec<Number>> for Number {
-    fn from(mut numbers: Vec<Number>) -> Self {
-        Self::pair(numbers.remove(0), numbers.remove(0))
+/// Values with their nesting depth, in order. Additions only reduce correctly when both operands
+/// are already reduced, so every depth must be at most 4.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct FlatNumber(Vec<(u32, u8)>);
+
+impl FlatNumber {
+    pub fn magnitude(&self) -> u32 {
+        let mut magnitudes: Vec<(u32, u8)> = Vec::new();
+        for &(value, depth) in &self.0 {
+            let mut magnitude = (value, depth);
+            while let Some(&(left, left_depth)) = magnitudes.last() {
+                if left_depth != magnitude.1 {
+                    break;
+                }
+                magnitudes.pop();
+                magnitude = (3 * left + 2 * magnitude.0, left_depth - 1);
+            }
+            magnitudes.push(magnitude);
+        }
+        magnitudes
+            .first()
+            .map(|(magnitude, _)| *magnitude)
+            .unwrap_or(0)
     }
-}
 
-impl FromStr for Number {
-    type Err = ();
+    pub fn reduce(&mut self) {
+        self.explode_all();
+        self.split_all();
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut numbers: Vec<Vec<Number>> = Vec::new();
+    fn explode_all(&mut self) {
+        let mut result: Vec<(u32, u8)> = Vec::with_capacity(self.0.len());
+        let mut carry = 0;
+        let mut elements = self.0.iter().copied();
+        while let Some((value, depth)) = elements.next() {
+            if depth > 4 {
+                if let Some(left) = result.last_mut() {
+                    left.0 += value + carry;
+                }
+                carry = elements.next().map(|(right, _)| right).unwrap_or(0);
+                result.push((0, depth - 1));
+            } else {
+                result.push((value + carry, depth));
+                carry = 0;
+            }
+        }
+        self.0 = result;
+    }
 
-        for c in s.chars() {
-            match c {
-                '[' => {
-                    numbers.push(Vec::new());
+    fn split_all(&mut self) {
+        let mut result: Vec<(u32, u8)> = Vec::with_capacity(self.0.len());
+        let mut to_visit = std::mem::take(&mut self.0);
+        to_visit.reverse();
+        while let Some((value, depth)) = to_visit.pop() {
+            if value < 10 {
+                result.push((value, depth));
+            } else if depth < 4 {
+                to_visit.push(((value - 1) / 2 + 1, depth + 1));
+                to_visit.push((value / 2, depth + 1));
+            } else {
+                if let Some(right) = to_visit.last_mut() {
+                    right.0 += (value - 1) / 2 + 1;
                 }
-                ']' => {
-                    let pair = numbers.pop().unwrap().into();
-                    if let Some(parent) = numbers.last_mut() {
-                        parent.push(pair);
-                    } else {
-                        return Ok(pair);
-                    }
+                to_visit.push((0, depth));
+                if let Some((left, left_depth)) = result.pop() {
+                    to_visit.push((left + value / 2, left_depth));
                 }
-                ',' => {}
-                _ => {
-                    let value = c.to_digit(10).unwrap();
-                    numbers.last_mut().unwrap().push(value.into())
+            }
+        }
+        self.0 = result;
+    }
+}
+
+impl Add for FlatNumber {
+    type Output = FlatNumber;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let mut number = Self(
+            self.0
+                .into_iter()
+                .chain(rhs.0)
+                .map(|(value, depth)| (value, depth + 1))
+                .collect(),
+        );
+        number.reduce();
+        number
+    }
+}
+
+impl From<&Number> for FlatNumber {
+    fn from(number: &Number) -> Self {
+        fn flatten(number: &Number, depth: u8, elements: &mut Vec<(u32, u8)>) {
+            match number {
+                Number::Value(value) => elements.push((*value, depth)),
+                Number::Pair(left, right) => {
+                    flatten(left, depth + 1, elements);
+                    flatten(right, depth + 1, elements);
                 }
             }
         }
 
-        Err(())
+        let mut elements = Vec::new();
+        flatten(number, 0, &mut elements);
+        debug_assert!(
+            elements.iter().all(|(_, depth)| *depth <= 4),
+            "{} should be reduced before being flattened",
+            number
+        );
+        Self(elements)
+    }
+}
+
+impl From<&FlatNumber> for Number {
+    fn from(number: &FlatNumber) -> Self {
+        let mut numbers: Vec<(Number, u8)> = Vec::new();
+        for &(value, depth) in &number.0 {
+            let mut current = (Number::Value(value), depth);
+            while numbers
+                .last()
+                .is_some_and(|(_, left_depth)| *left_depth == current.1)
+            {
+                let (left, left_depth) = numbers.pop().unwrap();
+                current = (Number::pair(left, current.0), left_depth - 1);
+            }
+            numbers.push(current);
+        }
+        numbers
+            .pop()
+            .map(|(number, _)| number)
+            .unwrap_or(Number::Value(0))
+    }
+}
+
+impl FromStr for Number {
+    type Err = NumberParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NumberParser::new(s).parse()
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum NumberParsingError {
+    UnexpectedChar {
+        column: usize,
+        expected: &'static str,
+        actual: char,
+    },
+    UnexpectedEnd {
+        expected: &'static str,
+    },
+    ValueTooLarge {
+        column: usize,
+    },
+}
+
+struct NumberParser {
+    source: Vec<char>,
+    position: usize,
+}
+
+impl NumberParser {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.chars().collect(),
+            position: 0,
+        }
+    }
+
+    pub fn parse(&mut self) -> Result<Number, NumberParsingError> {
+        let number = self.parse_number()?;
+        match self.peek() {
+            Some(c) => Err(self.unexpected_char("end of number", c)),
+            None => Ok(number),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Number, NumberParsingError> {
+        match self.peek() {
+            Some('[') => self.parse_pair(),
+            Some(c) if c.is_ascii_digit() => self.parse_value(),
+            Some(c) => Err(self.unexpected_char("'[' or a digit", c)),
+            None => Err(NumberParsingError::UnexpectedEnd {
+                expected: "'[' or a digit",
+            }),
+        }
+    }
+
+    fn parse_pair(&mut self) -> Result<Number, NumberParsingError> {
+        self.expect('[', "'['")?;
+        let left = self.parse_number()?;
+        self.expect(',', "','")?;
+        let right = self.parse_number()?;
+        self.expect(']', "']'")?;
+        Ok(Number::pair(left, right))
+    }
+
+    fn parse_value(&mut self) -> Result<Number, NumberParsingError> {
+        let column = self.position + 1;
+        let start = self.position;
+        while self
+            .source
+            .get(self.position)
+            .is_some_and(|c| c.is_ascii_digit())
+        {
+            self.position += 1;
+        }
+        self.source[start..self.position]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .map(Number::Value)
+            .map_err(|_| NumberParsingError::ValueTooLarge { column })
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        while self
+            .source
+            .get(self.position)
+            .is_some_and(|c| c.is_whitespace())
+        {
+            self.position += 1;
+        }
+        self.source.get(self.position).copied()
+    }
+
+    fn expect(
+        &mut self,
+        expected_char: char,
+        expected: &'static str,
+    ) -> Result<(), NumberParsingError> {
+        match self.peek() {
+            Some(c) if c == expected_char => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(c) => Err(self.unexpected_char(expected, c)),
+            None => Err(NumberParsingError::UnexpectedEnd { expected }),
+        }
+    }
+
+    fn unexpected_char(&self, expected: &'static str, actual: char) -> NumberParsingError {
+        NumberParsingError::UnexpectedChar {
+            column: self.position + 1,
+            expected,
+            actual,
+        }
     }
 }
 
@@ -202,6 +529,7 @@ impl Display for Number {
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
 
     lazy_static! {
         static ref LARGE_SAMPLE: Vec<Number> = input::read_lines(
@@ -217,6 +545,19 @@ mod test {
 [[[[4,2],2],6],[8,7]]"
                 .as_bytes()
         );
+        static ref HOMEWORK_SAMPLE: Vec<Number> = input::read_lines(
+            r"[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]
+[[[5,[2,8]],4],[5,[[9,9],0]]]
+[6,[[[6,2],[5,6]],[[7,6],[4,7]]]]
+[[[6,[0,7]],[0,9]],[4,[9,[9,0]]]]
+[[[7,[6,4]],[3,[1,3]]],[[[5,5],1],9]]
+[[6,[[7,3],[3,2]]],[[[3,8],[5,7]],4]]
+[[[[5,4],[7,7]],8],[[8,3],8]]
+[[9,3],[[9,9],[6,[4,9]]]]
+[[2,[[7,7],7]],[[5,8],[[9,3],[0,2]]]]
+[[[[5,2],5],[8,[3,7]]],[[5,[7,5]],[4,4]]]"
+                .as_bytes()
+        );
     }
 
     #[test]
@@ -262,4 +603,244 @@ mod test {
             "[[[[8,7],[7,7]],[[8,6],[7,7]]],[[[0,7],[6,6]],[8,7]]]"
         );
     }
+
+    #[test]
+    fn parse_multi_digit_values() {
+        let number: Number = "[[15,0],[1,123]]".parse().unwrap();
+
+        assert_eq!(
+            number,
+            Number::pair(Number::pair(15, 0), Number::pair(1, 123))
+        );
+    }
+
+    #[test]
+    fn parse_with_whitespace() {
+        let number: Number = " [ 1 ,\t[2, 3] ] ".parse().unwrap();
+
+        assert_eq!(number, Number::pair(1, Number::pair(2, 3)));
+    }
+
+    #[test]
+    fn parse_should_report_the_column_of_an_unexpected_char() {
+        let result = "[1,[2;3]]".parse::<Number>();
+
+        assert_eq!(
+            result,
+            Err(NumberParsingError::UnexpectedChar {
+                column: 6,
+                expected: "','",
+                actual: ';',
+            })
+        );
+    }
+
+    #[test]
+    fn parse_should_report_an_unexpected_end() {
+        let result = "[1,[2,3]".parse::<Number>();
+
+        assert_eq!(
+            result,
+            Err(NumberParsingError::UnexpectedEnd { expected: "']'" })
+        );
+    }
+
+    #[test]
+    fn parse_should_report_trailing_chars() {
+        let result = "[1,2]]".parse::<Number>();
+
+        assert_eq!(
+            result,
+            Err(NumberParsingError::UnexpectedChar {
+                column: 6,
+                expected: "end of number",
+                actual: ']',
+            })
+        );
+    }
+
+    #[test]
+    fn parse_should_report_values_too_large() {
+        let result = "[1,99999999999]".parse::<Number>();
+
+        assert_eq!(result, Err(NumberParsingError::ValueTooLarge { column: 4 }));
+    }
+
+    #[test]
+    fn best_pair_should_find_the_largest_magnitude_of_homework_sample() {
+        let numbers = HOMEWORK_SAMPLE
+            .iter()
+            .map(FlatNumber::from)
+            .collect::<Vec<_>>();
+
+        let (i, j, result) = best_pair(
+            &numbers,
+            |n1, n2| n1.clone() + n2.clone(),
+            FlatNumber::magnitude,
+        )
+        .unwrap();
+
+        assert_eq!((i, j), (8, 0));
+        assert_eq!(result.magnitude(), 3993);
+        assert_eq!(
+            Number::from(&result).to_string(),
+            "[[[[7,8],[6,6]],[[6,0],[7,7]]],[[[7,8],[8,8]],[[7,9],[0,6]]]]"
+        );
+    }
+
+    #[test]
+    fn best_pair_should_consider_both_orders_of_non_commutative_operations() {
+        let items = [1, 5, 3];
+
+        let result = best_pair(&items, |a, b| a * 10 - b, |r| *r);
+
+        assert_eq!(result, Some((1, 0, 49)));
+    }
+
+    #[test]
+    fn best_pair_should_return_the_first_pair_on_ties() {
+        let items = [2, 2, 2];
+
+        let result = best_pair(&items, |a, b| a + b, |r| *r);
+
+        assert_eq!(result, Some((0, 1, 4)));
+    }
+
+    #[test]
+    fn best_pair_should_return_none_without_pairs() {
+        assert_eq!(best_pair(&[1], |a, b| a + b, |r| *r), None);
+    }
+
+    #[test]
+    fn reduce_steps_should_follow_the_sample_reduction() {
+        use Direction::{Left as L, Right as R};
+        let number = Number::pair(
+            "[[[[4,3],4],4],[7,[[8,4],9]]]".parse::<Number>().unwrap(),
+            "[1,1]".parse::<Number>().unwrap(),
+        );
+
+        let steps = number
+            .reduce_steps()
+            .map(|(action, number)| (action, number.to_string()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            steps,
+            vec![
+                (
+                    ReduceAction::Explode(vec![L, L, L, L]),
+                    "[[[[0,7],4],[7,[[8,4],9]]],[1,1]]".to_string()
+                ),
+                (
+                    ReduceAction::Explode(vec![L, R, R, L]),
+                    "[[[[0,7],4],[15,[0,13]]],[1,1]]".to_string()
+                ),
+                (
+                    ReduceAction::Split(vec![L, R, L]),
+                    "[[[[0,7],4],[[7,8],[0,13]]],[1,1]]".to_string()
+                ),
+                (
+                    ReduceAction::Split(vec![L, R, R, R]),
+                    "[[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]".to_string()
+                ),
+                (
+                    ReduceAction::Explode(vec![L, R, R, R]),
+                    "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reduce_action_should_be_displayed_with_its_path() {
+        let action = ReduceAction::Split(vec![Direction::Left, Direction::Right]);
+
+        assert_eq!(action.to_string(), "split at LR");
+    }
+
+    #[test]
+    fn flat_number_should_convert_back_to_the_same_number() {
+        let number: Number = "[[[0,[4,5]],[0,0]],[[[4,5],[2,6]],[9,5]]]".parse().unwrap();
+
+        let flat = FlatNumber::from(&number);
+
+        assert_eq!(Number::from(&flat), number);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "should be reduced")]
+    fn flat_number_should_reject_numbers_nested_too_deeply() {
+        let number: Number = "[[[[[9,8],1],2],3],4]".parse().unwrap();
+
+        let _ = FlatNumber::from(&number);
+    }
+
+    #[test]
+    fn flat_sum_should_be_the_same_as_tree_sum_for_large_sample() {
+        let tree = sum_numbers(LARGE_SAMPLE.clone());
+
+        let flat = LARGE_SAMPLE
+            .iter()
+            .map(FlatNumber::from)
+            .reduce(|n1, n2| n1 + n2)
+            .unwrap();
+
+        assert_eq!(Number::from(&flat), tree);
+        assert_eq!(flat.magnitude(), tree.magnitude());
+    }
+
+    #[test]
+    fn flat_magnitude_should_be_4140_for_homework_sample() {
+        let result = HOMEWORK_SAMPLE
+            .iter()
+            .map(FlatNumber::from)
+            .reduce(|n1, n2| n1 + n2)
+            .unwrap();
+
+        assert_eq!(result.magnitude(), 4140);
+        assert_eq!(
+            Number::from(&result).to_string(),
+            "[[[[6,6],[7,6]],[[7,7],[7,0]]],[[[7,7],[7,7]],[[7,8],[9,9]]]]"
+        );
+    }
+
+    fn arbitrary_reduced_number() -> impl Strategy<Value = Number> {
+        (0..10u32)
+            .prop_map(Number::Value)
+            .prop_recursive(4, 64, 2, |inner| {
+                (inner.clone(), inner).prop_map(|(left, right)| Number::pair(left, right))
+            })
+            .prop_filter("a snailfish number is a pair", |number| {
+                matches!(number, Number::Pair(..))
+            })
+    }
+
+    fn arbitrary_number() -> impl Strategy<Value = Number> {
+        any::<u32>()
+            .prop_map(Number::Value)
+            .prop_recursive(6, 64, 2, |inner| {
+                (inner.clone(), inner).prop_map(|(left, right)| Number::pair(left, right))
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn displayed_number_should_parse_back_to_the_same_number(number in arbitrary_number()) {
+            prop_assert_eq!(number.to_string().parse::<Number>(), Ok(number));
+        }
+
+        #[test]
+        fn flat_addition_should_be_the_same_as_tree_addition(
+            left in arbitrary_reduced_number(),
+            right in arbitrary_reduced_number(),
+        ) {
+            let flat = FlatNumber::from(&left) + FlatNumber::from(&right);
+
+            let tree = left + right;
+
+            prop_assert_eq!(flat.magnitude(), tree.magnitude());
+            prop_assert_eq!(Number::from(&flat), tree);
+        }
+    }
 }
@@ -1,12 +1,18 @@
-use itertools::{FoldWhile, Itertools};
+use itertools::Itertools;
 use lazy_static::lazy_static;
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::ops::Sub;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+use std::path::Path;
 
-use super::{input, Puzzle};
+use super::registration::{
+    transformation_chain, Aligner, Alignment, PointCloud, Position, Rotation, Transform,
+};
+use super::{input, Options, Puzzle};
 
 lazy_static! {
-    static ref SCANNERS: Vec<Scanner> = parse_scanners(&input::read_file("day19"));
+    static ref SCANNERS: Vec<PointCloud> = parse_scanners(&input::read_file("day19"));
     static ref NORMALIZED_SCANNERS: Vec<Scanner> = normalize_scanners(&SCANNERS);
 }
 
@@ -28,85 +34,136 @@ impl Puzzle for Day19 {
             find_highest_manhattan_distance(&NORMALIZED_SCANNERS)
         )
     }
-}
-
-fn parse_scanners(input: &str) -> Vec<Scanner> {
-    let mut scanners = Vec::new();
-    let mut current_beacons = Vec::new();
 
-    for line in input.lines().filter(|line| !line.is_empty()) {
-        if line.starts_with("---") {
-            if !current_beacons.is_empty() {
-                scanners.push(Scanner::new(std::mem::take(&mut current_beacons)))
+    fn handle_options(&self, options: &Options) {
+        if options.has("align") {
+            let mut aligner = Aligner::default();
+            if let Some(overlap) = options.value("overlap") {
+                match aligner.with_overlap(overlap.parse().expect("Overlap should be a number")) {
+                    Ok(with_overlap) => aligner = with_overlap,
+                    Err(error) => {
+                        println!("{:?}", error);
+                        return;
+                    }
+                }
+            }
+            if let Some(tolerance) = options.value("tolerance") {
+                match aligner
+                    .with_tolerance(tolerance.parse().expect("Tolerance should be a number"))
+                {
+                    Ok(with_tolerance) => aligner = with_tolerance,
+                    Err(error) => {
+                        println!("{:?}", error);
+                        return;
+                    }
+                }
+            }
+            match aligner.align(&SCANNERS) {
+                Ok(alignments) => {
+                    for (i, alignment) in alignments.iter().enumerate() {
+                        println!(
+                            "Scanner {}: position {}, rotation {}, {} inliers",
+                            i,
+                            alignment.transform.translation,
+                            alignment.transform.rotation,
+                            alignment.inliers.len()
+                        );
+                    }
+                }
+                Err(error) => println!("{:?}", error),
             }
-        } else {
-            current_beacons.push(line.into());
+        }
+        if let Some(seed) = options.value("generate") {
+            let mut generator =
+                ReportGenerator::new(seed.parse().expect("Seed should be a number"));
+            if let Some(scanners) = options.value("scanners") {
+                generator =
+                    generator.with_scanners(scanners.parse().expect("Scanners should be a number"));
+            }
+            if let Some(beacons) = options.value("beacons") {
+                generator =
+                    generator.with_beacons(beacons.parse().expect("Beacons should be a number"));
+            }
+            if let Some(range) = options.value("range") {
+                generator = generator.with_range(range.parse().expect("Range should be a number"));
+            }
+            if let Some(overlap) = options.value("overlap") {
+                generator =
+                    generator.with_overlap(overlap.parse().expect("Overlap should be a number"));
+            }
+            match generator.generate() {
+                Ok(report) => print!("{}", report.to_report()),
+                Err(error) => println!("{:?}", error),
+            }
+        }
+        if let Some(file) = options.value("export") {
+            let path = Path::new(file);
+            let frame = match options.value("rotate").map(str::parse) {
+                Some(Ok(rotation)) => Transform::new(rotation, Position::default()),
+                Some(Err(())) => {
+                    println!(
+                        "Invalid rotation, expected a rotation matrix like [0,-1,0;1,0,0;0,0,1]"
+                    );
+                    return;
+                }
+                None => Transform::IDENTITY,
+            };
+            let alignments = Aligner::default().align(&SCANNERS).unwrap();
+            let scanners = SCANNERS
+                .iter()
+                .zip(&alignments)
+                .map(|(beacons, alignment)| Scanner::new(beacons, alignment.transform.then(&frame)))
+                .collect::<Vec<_>>();
+            let points = match path.extension().and_then(|extension| extension.to_str()) {
+                Some("ply") => to_ply(&scanners),
+                Some("xyz") => to_xyz(&scanners),
+                _ => {
+                    println!("Unsupported export format for {}, use .ply or .xyz", file);
+                    return;
+                }
+            };
+            let sidecar = path.with_extension("json");
+            std::fs::write(path, points).unwrap();
+            std::fs::write(&sidecar, alignments_to_json(&alignments, &frame)).unwrap();
+            println!(
+                "Reconstruction written to {} and {}",
+                file,
+                sidecar.display()
+            );
         }
     }
-
-    if !current_beacons.is_empty() {
-        scanners.push(Scanner::new(current_beacons));
-    }
-
-    scanners
 }
 
-fn normalize_scanners(scanners: &[Scanner]) -> Vec<Scanner> {
-    let mut normalized_scanners = scanners.first().cloned().into_iter().collect::<Vec<_>>();
-    let mut to_normalize = scanners
-        .iter()
-        .skip(1)
-        .cloned()
-        .into_iter()
-        .collect::<VecDeque<_>>();
+fn parse_scanners(input: &str) -> Vec<PointCloud> {
+    let mut scanners: Vec<Vec<Position>> = Vec::new();
 
-    while let Some(scanner) = to_normalize.pop_front() {
-        let result = normalized_scanners
-            .iter()
-            .find_map(|normalized_scanner| scanner.find_common_beacons(normalized_scanner));
-        if let Some(common_beacons) = result {
-            let transformation = find_matching_transformation(&common_beacons);
-            if let Some((transformation, position)) = transformation {
-                normalized_scanners.push(scanner.transform(transformation, position));
-                continue;
-            }
+    for line in input.lines().filter(|line| !line.is_empty()) {
+        if line.starts_with("---") {
+            scanners.push(Vec::new());
+        } else if let Some(beacons) = scanners.last_mut() {
+            beacons.push(line.into());
         }
-        to_normalize.push_back(scanner);
     }
 
-    normalized_scanners
+    scanners.into_iter().map(PointCloud::new).collect()
 }
 
-fn find_matching_transformation(
-    common_beacons: &HashMap<Position, Position>,
-) -> Option<(&dyn Transformation, Position)> {
-    TRANSFORMATIONS.iter().find_map(|transformation| {
-        get_scanner_position(common_beacons, transformation.as_ref())
-            .map(|position| (transformation.as_ref(), position))
-    })
-}
+fn normalize_scanners(scanners: &[PointCloud]) -> Vec<Scanner> {
+    let alignments = Aligner::default()
+        .align(scanners)
+        .expect("All scanners should overlap with the first one");
 
-fn get_scanner_position(
-    common_beacons: &HashMap<Position, Position>,
-    transformation: &dyn Transformation,
-) -> Option<Position> {
-    common_beacons
+    scanners
         .iter()
-        .map(|(position, &normalized_position)| {
-            transformation.transform(*position) - normalized_position
-        })
-        .fold_while(None, |scanner_position, diff| match scanner_position {
-            Some(position) if position == diff => FoldWhile::Continue(Some(position)),
-            Some(_) => FoldWhile::Done(None),
-            None => FoldWhile::Continue(Some(diff)),
-        })
-        .into_inner()
+        .zip(alignments)
+        .map(|(beacons, alignment)| Scanner::new(beacons, alignment.transform))
+        .collect()
 }
 
 fn get_all_beacons(scanners: &[Scanner]) -> HashSet<Position> {
     scanners
         .iter()
-        .flat_map(|scanner| scanner.beacons.keys())
+        .flat_map(|scanner| scanner.beacons.points())
         .copied()
         .collect()
 }
@@ -120,186 +177,279 @@ fn find_highest_manhattan_distance(scanners: &[Scanner]) -> i32 {
         .unwrap()
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-struct Position {
-    x: i32,
-    y: i32,
-    z: i32,
+fn to_ply(scanners: &[Scanner]) -> String {
+    let beacons = get_all_beacons(scanners)
+        .into_iter()
+        .sorted()
+        .map(|Position { x, y, z }| format!("{} {} {} 255 255 255 -1\n", x, y, z))
+        .collect::<Vec<_>>();
+    let scanner_positions = scanners.iter().enumerate().map(|(i, scanner)| {
+        let Position { x, y, z } = scanner.position;
+        format!("{} {} {} 255 0 0 {}\n", x, y, z, i)
+    });
+    format!(
+        "ply\n\
+         format ascii 1.0\n\
+         element vertex {}\n\
+         property int x\n\
+         property int y\n\
+         property int z\n\
+         property uchar red\n\
+         property uchar green\n\
+         property uchar blue\n\
+         property int scanner\n\
+         end_header\n\
+         {}{}",
+        beacons.len() + scanners.len(),
+        beacons.concat(),
+        scanner_positions.collect::<String>()
+    )
 }
 
-impl Position {
-    fn manhattan_distance(&self, other: &Position) -> i32 {
-        (self.x - other.x).abs() + (self.y - other.y).abs() + (self.z - other.z).abs()
-    }
+fn to_xyz(scanners: &[Scanner]) -> String {
+    let beacons = get_all_beacons(scanners)
+        .into_iter()
+        .sorted()
+        .map(|Position { x, y, z }| format!("B {} {} {}\n", x, y, z))
+        .collect::<Vec<_>>();
+    let scanner_positions = scanners.iter().enumerate().map(|(i, scanner)| {
+        let Position { x, y, z } = scanner.position;
+        format!("S{} {} {} {}\n", i, x, y, z)
+    });
+    format!(
+        "{}\nB: beacon, S<n>: scanner n\n{}{}",
+        beacons.len() + scanners.len(),
+        beacons.concat(),
+        scanner_positions.collect::<String>()
+    )
 }
 
-impl Sub for Position {
-    type Output = Position;
-
-    fn sub(self, rhs: Self) -> Self::Output {
-        Position {
-            x: self.x - rhs.x,
-            y: self.y - rhs.y,
-            z: self.z - rhs.z,
-        }
-    }
+fn alignments_to_json(alignments: &[Alignment], frame: &Transform) -> String {
+    let scanners = alignments.iter().enumerate().map(|(i, alignment)| {
+        let transform = alignment.transform.then(frame);
+        let chain = export_chain(alignments, i, frame)
+            .into_iter()
+            .map(|(from, to, transform)| {
+                format!(
+                    "{{\"from\": {}, \"to\": {}, \"rotation\": {:?}, \"translation\": {}}}",
+                    from,
+                    to.map(|to| to.to_string())
+                        .unwrap_or_else(|| "\"export\"".to_string()),
+                    transform.rotation.matrix(),
+                    json_position(&transform.translation)
+                )
+            })
+            .join(", ");
+        format!(
+            "  {{\"scanner\": {}, \"position\": {}, \"rotation\": {:?}, \"chain\": [{}]}}",
+            i,
+            json_position(&transform.translation),
+            transform.rotation.matrix(),
+            chain
+        )
+    });
+    format!("[\n{}\n]\n", scanners.collect::<Vec<_>>().join(",\n"))
 }
 
-impl FromIterator<i32> for Position {
-    fn from_iter<T: IntoIterator<Item = i32>>(iter: T) -> Self {
-        let mut iter = iter.into_iter();
-        Self {
-            x: iter.next().unwrap(),
-            y: iter.next().unwrap(),
-            z: iter.next().unwrap(),
-        }
+fn export_chain(
+    alignments: &[Alignment],
+    scanner: usize,
+    frame: &Transform,
+) -> Vec<(usize, Option<usize>, Transform)> {
+    let mut chain = transformation_chain(alignments, scanner)
+        .into_iter()
+        .map(|(from, to, transform)| (from, Some(to), transform))
+        .collect::<Vec<_>>();
+    if *frame != Transform::IDENTITY {
+        chain.push((0, None, *frame));
     }
+    chain
 }
 
-impl From<&str> for Position {
-    fn from(line: &str) -> Self {
-        line.split(',')
-            .take(3)
-            .map(|s| s.parse::<i32>().unwrap())
-            .collect()
-    }
+fn json_position(position: &Position) -> String {
+    format!("[{}, {}, {}]", position.x, position.y, position.z)
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
-struct Scanner {
-    beacons: HashMap<Position, HashSet<i32>>,
-    position: Position,
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct ReportGenerator {
+    seed: u64,
+    scanners: usize,
+    beacons: usize,
+    range: i32,
+    overlap: usize,
 }
 
-impl Scanner {
-    fn new(beacons: Vec<Position>) -> Self {
+impl ReportGenerator {
+    fn new(seed: u64) -> Self {
         Self {
-            beacons: beacons
-                .iter()
-                .map(|beacon| (*beacon, get_distances(beacon, &beacons)))
-                .collect(),
-            position: Position { x: 0, y: 0, z: 0 },
+            seed,
+            scanners: 5,
+            beacons: 15,
+            range: 1000,
+            overlap: 12,
         }
     }
 
-    fn find_common_beacons(&self, other: &Scanner) -> Option<HashMap<Position, Position>> {
-        let common_beacons = self
-            .beacons
-            .iter()
-            .flat_map(|(beacon, distances)| {
-                other
-                    .beacons
-                    .iter()
-                    .find(|(_, other_distances)| have_common_distances(distances, other_distances))
-                    .map(|(other_beacon, _)| (*beacon, *other_beacon))
-            })
-            .collect::<HashMap<_, _>>();
+    fn with_scanners(self, scanners: usize) -> Self {
+        Self { scanners, ..self }
+    }
 
-        (common_beacons.len() >= 12).then(|| common_beacons)
+    fn with_beacons(self, beacons: usize) -> Self {
+        Self { beacons, ..self }
     }
 
-    fn transform(&self, transformation: &dyn Transformation, position: Position) -> Self {
-        Self {
-            beacons: self
-                .beacons
-                .iter()
-                .map(|(p, d)| (transformation.transform(*p) - position, d.clone()))
-                .collect(),
-            position,
+    fn with_range(self, range: i32) -> Self {
+        Self { range, ..self }
+    }
+
+    fn with_overlap(self, overlap: usize) -> Self {
+        Self { overlap, ..self }
+    }
+
+    fn generate(&self) -> Result<SyntheticReport, GenerationError> {
+        let positions_in_range = u64::try_from(self.range)
+            .ok()
+            .and_then(|range| (range + 1).checked_pow(3));
+        let beacons = self.beacons.max(self.overlap);
+        if self.range < 0 || positions_in_range.is_some_and(|positions| positions < beacons as u64)
+        {
+            return Err(GenerationError::RangeTooSmall {
+                range: self.range,
+                beacons,
+            });
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let rotations = Rotation::all();
+        let mut scanners = vec![Transform::IDENTITY];
+        let mut beacons = HashSet::new();
+
+        for _ in 1..self.scanners {
+            let parent = scanners[rng.gen_range(0..scanners.len())].translation;
+            let offset = (0..3)
+                .map(|_| rng.gen_range(-self.range..=self.range))
+                .collect::<Position>();
+            let position = parent + offset;
+            let common_beacons = self.random_beacons(&mut rng, self.overlap, |rng| {
+                [
+                    (parent.x, position.x),
+                    (parent.y, position.y),
+                    (parent.z, position.z),
+                ]
+                .into_iter()
+                .map(|(a, b)| rng.gen_range(a.max(b) - self.range..=a.min(b) + self.range))
+                .collect()
+            });
+            beacons.extend(common_beacons);
+            scanners.push(Transform::new(
+                *rotations.choose(&mut rng).unwrap(),
+                position,
+            ));
+        }
+
+        for scanner in &scanners {
+            let center = scanner.translation;
+            beacons.extend(self.random_beacons(&mut rng, self.beacons, |rng| {
+                center
+                    + (0..3)
+                        .map(|_| rng.gen_range(-self.range..=self.range))
+                        .collect()
+            }));
         }
+
+        Ok(SyntheticReport {
+            range: self.range,
+            scanners,
+            beacons,
+        })
     }
-}
 
-fn get_distances(beacon: &Position, beacons: &[Position]) -> HashSet<i32> {
-    beacons
-        .iter()
-        .filter(|b| beacon != *b)
-        .map(|b| square_distance(beacon, b))
-        .collect()
+    fn random_beacons(
+        &self,
+        rng: &mut StdRng,
+        count: usize,
+        mut random_beacon: impl FnMut(&mut StdRng) -> Position,
+    ) -> HashSet<Position> {
+        let mut beacons = HashSet::new();
+        while beacons.len() < count {
+            beacons.insert(random_beacon(rng));
+        }
+        beacons
+    }
 }
 
-fn square_distance(b1: &Position, b2: &Position) -> i32 {
-    (b1.x - b2.x).pow(2) + (b1.y - b2.y).pow(2) + (b1.z - b2.z).pow(2)
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum GenerationError {
+    RangeTooSmall { range: i32, beacons: usize },
 }
 
-fn have_common_distances(d1: &HashSet<i32>, d2: &HashSet<i32>) -> bool {
-    d1.iter().filter(|d| d2.contains(*d)).take(11).count() == 11
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct SyntheticReport {
+    range: i32,
+    scanners: Vec<Transform>,
+    beacons: HashSet<Position>,
 }
 
-trait Transformation: Sync {
-    fn transform(&self, position: Position) -> Position;
+impl SyntheticReport {
+    fn to_report(&self) -> String {
+        self.scanners
+            .iter()
+            .enumerate()
+            .map(|(i, scanner)| {
+                let inverse = scanner.inverse();
+                let beacons = self
+                    .beacons
+                    .iter()
+                    .filter(|beacon| {
+                        let Position { x, y, z } = **beacon - scanner.translation;
+                        [x, y, z].iter().all(|c| c.abs() <= self.range)
+                    })
+                    .sorted()
+                    .map(|beacon| inverse.apply(*beacon).to_string())
+                    .join("\n");
+                format!("--- scanner {} ---\n{}\n", i, beacons)
+            })
+            .join("\n")
+    }
 }
 
-impl<F> Transformation for F
-where
-    F: Fn(Position) -> Position + Sync,
-{
-    fn transform(&self, position: Position) -> Position {
-        self(position)
-    }
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Scanner {
+    beacons: PointCloud,
+    position: Position,
 }
 
-lazy_static! {
-    static ref TRANSFORMATIONS: [Box<dyn Transformation>; 24] = [
-        Box::new(|Position { x, y, z }| Position { x, y, z }),
-        Box::new(|Position { x, y, z }| Position { x, y: -z, z: y }),
-        Box::new(|Position { x, y, z }| Position { x, y: -y, z: -z }),
-        Box::new(|Position { x, y, z }| Position { x, y: z, z: -y }),
-        Box::new(|Position { x, y, z }| Position { x: -y, y: x, z }),
-        Box::new(|Position { x, y, z }| Position { x: z, y: x, z: y }),
-        Box::new(|Position { x, y, z }| Position { x: y, y: x, z: -z }),
-        Box::new(|Position { x, y, z }| Position { x: -z, y: x, z: -y }),
-        Box::new(|Position { x, y, z }| Position { x: -x, y: -y, z }),
-        Box::new(|Position { x, y, z }| Position {
-            x: -x,
-            y: -z,
-            z: -y
-        }),
-        Box::new(|Position { x, y, z }| Position { x: -x, y, z: -z }),
-        Box::new(|Position { x, y, z }| Position { x: -x, y: z, z: y }),
-        Box::new(|Position { x, y, z }| Position { x: y, y: -x, z }),
-        Box::new(|Position { x, y, z }| Position { x: z, y: -x, z: -y }),
-        Box::new(|Position { x, y, z }| Position {
-            x: -y,
-            y: -x,
-            z: -z
-        }),
-        Box::new(|Position { x, y, z }| Position { x: -z, y: -x, z: y }),
-        Box::new(|Position { x, y, z }| Position { x: -z, y, z: x }),
-        Box::new(|Position { x, y, z }| Position { x: y, y: z, z: x }),
-        Box::new(|Position { x, y, z }| Position { x: z, y: -y, z: x }),
-        Box::new(|Position { x, y, z }| Position { x: -y, y: -z, z: x }),
-        Box::new(|Position { x, y, z }| Position {
-            x: -z,
-            y: -y,
-            z: -x
-        }),
-        Box::new(|Position { x, y, z }| Position { x: -y, y: z, z: -x }),
-        Box::new(|Position { x, y, z }| Position { x: z, y, z: -x }),
-        Box::new(|Position { x, y, z }| Position { x: y, y: -z, z: -x }),
-    ];
+impl Scanner {
+    fn new(beacons: &PointCloud, transform: Transform) -> Self {
+        Self {
+            beacons: beacons.transform(&transform),
+            position: transform.translation,
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use super::super::registration::{find_transform, AlignmentError};
     use super::*;
+    use proptest::prelude::*;
 
     lazy_static! {
-        static ref SAMPLE: Vec<Scanner> = parse_scanners(&input::read_file("day19_sample"));
+        static ref SAMPLE: Vec<PointCloud> = parse_scanners(&input::read_file("day19_sample"));
     }
 
     #[test]
     fn first_two_scanners_of_sample_should_have_12_common_beacons() {
-        let common_beacons = SAMPLE[0].find_common_beacons(&SAMPLE[1]).unwrap();
+        let common_beacons = SAMPLE[0].find_common_points(&SAMPLE[1], 12).unwrap();
 
         assert_eq!(common_beacons.len(), 12);
     }
 
     #[test]
     fn transformation_to_normalize_second_scanner() {
-        let common_beacons = SAMPLE[0].find_common_beacons(&SAMPLE[1]).unwrap();
+        let common_beacons = SAMPLE[0].find_common_points(&SAMPLE[1], 12).unwrap();
 
-        let transformation = find_matching_transformation(&common_beacons);
+        let transformation = find_transform(&common_beacons, 0.0);
 
         assert!(transformation.is_some())
     }
@@ -324,10 +474,58 @@ mod test {
         ];
         assert!(expected
             .iter()
-            .all(|beacon| normalized_scanners[0].beacons.contains_key(beacon)));
+            .all(|beacon| normalized_scanners[0].beacons.contains(beacon)));
         assert!(expected
             .iter()
-            .all(|beacon| normalized_scanners[1].beacons.contains_key(beacon)));
+            .all(|beacon| normalized_scanners[1].beacons.contains(beacon)));
+    }
+
+    #[test]
+    fn align_should_find_sample_scanner_positions_relative_to_first_scanner() {
+        let alignments = Aligner::default().align(&SAMPLE).unwrap();
+
+        let positions = alignments
+            .iter()
+            .map(|alignment| alignment.transform.translation)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            positions,
+            vec![
+                Position::new(0, 0, 0),
+                Position::new(68, -1246, -43),
+                Position::new(1105, -1205, 1229),
+                Position::new(-92, -2380, -20),
+                Position::new(-20, -1133, 1061),
+            ]
+        );
+    }
+
+    #[test]
+    fn align_should_fail_when_overlap_is_higher_than_common_beacons() {
+        let transforms = Aligner::default()
+            .with_overlap(13)
+            .and_then(|aligner| aligner.align(&SAMPLE[0..=1]));
+
+        assert_eq!(
+            transforms,
+            Err(AlignmentError::Disconnected { clouds: vec![1] })
+        );
+    }
+
+    #[test]
+    fn align_should_accept_lower_overlap() {
+        let transforms = |overlap| {
+            Aligner::default()
+                .with_overlap(overlap)
+                .unwrap()
+                .align(&SAMPLE)
+                .unwrap()
+                .into_iter()
+                .map(|alignment| alignment.transform)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(transforms(6), transforms(12));
     }
 
     #[test]
@@ -346,4 +544,216 @@ mod test {
 
         assert_eq!(result, 3621);
     }
+
+    #[test]
+    fn align_should_report_inliers_of_each_scanner() {
+        let alignments = Aligner::default().align(&SAMPLE[0..=1]).unwrap();
+
+        assert!(alignments[0].inliers.is_empty());
+        assert_eq!(alignments[1].inliers.len(), 12);
+        assert!(alignments[1].inliers.contains(&"686,422,578".into()));
+    }
+
+    #[test]
+    fn align_with_tolerance_should_survive_a_noisy_sample_beacon() {
+        let report = input::read_file("day19_sample").replacen("686,422,578", "687,422,578", 1);
+        let scanners = parse_scanners(&report);
+
+        let alignments = Aligner::default()
+            .with_tolerance(0.25)
+            .unwrap()
+            .align(&scanners[0..=1])
+            .unwrap();
+
+        assert_eq!(
+            Aligner::default().align(&scanners[0..=1]),
+            Err(AlignmentError::Disconnected { clouds: vec![1] })
+        );
+        assert_eq!(
+            alignments[1].transform.translation,
+            Position::new(68, -1246, -43)
+        );
+        assert_eq!(alignments[1].inliers.len(), 11);
+    }
+
+    #[test]
+    fn align_with_tolerance_should_find_same_sample_positions() {
+        let alignments = Aligner::default()
+            .with_tolerance(0.25)
+            .unwrap()
+            .align(&SAMPLE)
+            .unwrap();
+
+        assert_eq!(
+            alignments[4].transform.translation,
+            Position::new(-20, -1133, 1061)
+        );
+    }
+
+    #[test]
+    fn transformation_chain_should_lead_back_to_first_scanner() {
+        let alignments = Aligner::default().align(&SAMPLE).unwrap();
+
+        let chain = transformation_chain(&alignments, 2)
+            .into_iter()
+            .map(|(from, to, _)| (from, to))
+            .collect::<Vec<_>>();
+
+        assert_eq!(chain, vec![(2, 4), (4, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn to_ply_should_write_beacons_and_tagged_scanners() {
+        let normalized_scanners = normalize_scanners(&SAMPLE);
+
+        let ply = to_ply(&normalized_scanners);
+
+        let lines = ply.lines().collect::<Vec<_>>();
+        assert_eq!(lines[2], "element vertex 84");
+        assert_eq!(lines[10], "end_header");
+        assert_eq!(lines.len(), 11 + 84);
+        assert_eq!(lines[11], "-892 524 684 255 255 255 -1");
+        assert_eq!(lines[90], "0 0 0 255 0 0 0");
+        assert_eq!(lines[91], "68 -1246 -43 255 0 0 1");
+    }
+
+    #[test]
+    fn to_xyz_should_write_beacons_and_tagged_scanners() {
+        let normalized_scanners = normalize_scanners(&SAMPLE);
+
+        let xyz = to_xyz(&normalized_scanners);
+
+        let lines = xyz.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "84");
+        assert_eq!(lines.len(), 2 + 84);
+        assert_eq!(lines[2], "B -892 524 684");
+        assert_eq!(lines[85], "S4 -20 -1133 1061");
+    }
+
+    #[test]
+    fn alignments_to_json_should_describe_each_transformation_chain() {
+        let alignments = Aligner::default().align(&SAMPLE[0..=1]).unwrap();
+
+        let json = alignments_to_json(&alignments, &Transform::IDENTITY);
+
+        assert_eq!(
+            json,
+            "[\n  \
+             {\"scanner\": 0, \"position\": [0, 0, 0], \"rotation\": [[1, 0, 0], [0, 1, 0], [0, 0, 1]], \"chain\": []},\n  \
+             {\"scanner\": 1, \"position\": [68, -1246, -43], \"rotation\": [[-1, 0, 0], [0, 1, 0], [0, 0, -1]], \"chain\": [\
+             {\"from\": 1, \"to\": 0, \"rotation\": [[-1, 0, 0], [0, 1, 0], [0, 0, -1]], \"translation\": [68, -1246, -43]}]}\n\
+             ]\n"
+        );
+    }
+
+    #[test]
+    fn alignments_to_json_should_end_chains_with_the_export_frame() {
+        let alignments = Aligner::default().align(&SAMPLE[0..=1]).unwrap();
+        let frame = Transform::new("[0,-1,0;1,0,0;0,0,1]".parse().unwrap(), Position::default());
+
+        let json = alignments_to_json(&alignments, &frame);
+
+        assert_eq!(
+            json,
+            "[\n  \
+             {\"scanner\": 0, \"position\": [0, 0, 0], \"rotation\": [[0, -1, 0], [1, 0, 0], [0, 0, 1]], \"chain\": [\
+             {\"from\": 0, \"to\": \"export\", \"rotation\": [[0, -1, 0], [1, 0, 0], [0, 0, 1]], \"translation\": [0, 0, 0]}]},\n  \
+             {\"scanner\": 1, \"position\": [1246, 68, -43], \"rotation\": [[0, -1, 0], [-1, 0, 0], [0, 0, -1]], \"chain\": [\
+             {\"from\": 1, \"to\": 0, \"rotation\": [[-1, 0, 0], [0, 1, 0], [0, 0, -1]], \"translation\": [68, -1246, -43]}, \
+             {\"from\": 0, \"to\": \"export\", \"rotation\": [[0, -1, 0], [1, 0, 0], [0, 0, 1]], \"translation\": [0, 0, 0]}]}\n\
+             ]\n"
+        );
+    }
+
+    #[test]
+    fn export_chain_should_compose_into_the_exported_transform() {
+        let alignments = Aligner::default().align(&SAMPLE).unwrap();
+        let frame = Transform::new("[0,0,1;0,1,0;-1,0,0]".parse().unwrap(), Position::default());
+
+        for (i, alignment) in alignments.iter().enumerate() {
+            let composed = export_chain(&alignments, i, &frame)
+                .into_iter()
+                .fold(Transform::IDENTITY, |composed, (_, _, transform)| {
+                    composed.then(&transform)
+                });
+
+            assert_eq!(composed, alignment.transform.then(&frame));
+        }
+    }
+
+    #[test]
+    fn generated_report_should_be_parsable_scanner_reports() {
+        let synthetic = ReportGenerator::new(42)
+            .with_scanners(3)
+            .generate()
+            .unwrap();
+
+        let report = synthetic.to_report();
+
+        assert!(report.starts_with("--- scanner 0 ---\n"));
+        assert!(report.contains("\n\n--- scanner 2 ---\n"));
+        assert_eq!(parse_scanners(&report).len(), 3);
+    }
+
+    #[test]
+    fn generator_should_be_deterministic_for_a_seed() {
+        let generator = ReportGenerator::new(7).with_beacons(5).with_range(500);
+
+        assert_eq!(generator.generate(), generator.generate());
+        assert_ne!(generator.generate(), ReportGenerator::new(8).generate());
+    }
+
+    #[test]
+    fn generator_should_reject_ranges_too_small_for_the_beacons() {
+        let generator = ReportGenerator::new(7).with_range(0).with_overlap(2);
+
+        assert_eq!(
+            generator.generate(),
+            Err(GenerationError::RangeTooSmall {
+                range: 0,
+                beacons: 15
+            })
+        );
+        assert!(ReportGenerator::new(7)
+            .with_range(1)
+            .with_beacons(8)
+            .with_overlap(8)
+            .generate()
+            .is_ok());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(16))]
+
+        #[test]
+        fn normalize_scanners_should_recover_hidden_positions(
+            seed in any::<u64>(),
+            scanners in 1usize..6,
+            beacons in 0usize..20,
+            range in 500i32..1500,
+            overlap in 12usize..16,
+        ) {
+            let synthetic = ReportGenerator::new(seed)
+                .with_scanners(scanners)
+                .with_beacons(beacons)
+                .with_range(range)
+                .with_overlap(overlap)
+                .generate()
+                .unwrap();
+
+            let normalized_scanners = normalize_scanners(&parse_scanners(&synthetic.to_report()));
+
+            let positions = normalized_scanners
+                .iter()
+                .map(|scanner| scanner.position)
+                .collect::<Vec<_>>();
+            let hidden_positions = synthetic
+                .scanners
+                .iter()
+                .map(|scanner| scanner.translation)
+                .collect::<Vec<_>>();
+            prop_assert_eq!(positions, hidden_positions);
+            prop_assert_eq!(get_all_beacons(&normalized_scanners), synthetic.beacons);
+        }
+    }
 }
@@ -1,11 +1,16 @@
+use itertools::Itertools;
 use lazy_static::lazy_static;
 use std::fmt::{Display, Formatter, Write};
+use std::hash::Hash;
+use std::str::FromStr;
 
-use super::{input, Puzzle};
+use super::automaton::{CellularAutomaton, Life, Simulation};
+use super::{input, Options, Puzzle};
 
 lazy_static! {
-    static ref IMAGE_ENHANCEMENT: [u8; 512] = to_array(parse_image_data("####....#.....##.####..#.##.###.########.##.#..#.##.#...#..##.######..#......#..###.#.##.####.#.#.#....######.###...###.#.###.####..###.......#..#.#.#.#.#.####..####.#..####.#..####..##.#.#.#.###..##..#....#...###.#....###....##.###...##..#..#..#...##...#.#..#..###...####.#.#.###..#.#.#..###.##.##.#..###...#.#.#.##...#...#..#...##..###..###..#...###.#....#.##.#.####...##...##.#.####.#####.##...#######.###..##.#####.##.....####.#######.#.#.##....#...##...#..##.###.######.#######.#.#.#....#..##.###.#..##..##."));
-    static ref INPUT: Image = input::read_lines_from_file::<String>("day20").into();
+    static ref INPUT: TrenchMap = input::read_file("day20")
+        .parse()
+        .expect("Input should be a valid trench map");
 }
 
 pub struct Day20;
@@ -17,8 +22,9 @@ impl Puzzle for Day20 {
 
     fn part_one(&self) -> String {
         let enhanced = INPUT
-            .enhance(IMAGE_ENHANCEMENT.as_ref())
-            .enhance(IMAGE_ENHANCEMENT.as_ref());
+            .image
+            .enhance(&INPUT.image_enhancement)
+            .enhance(&INPUT.image_enhancement);
         format!(
             "Number of lit pixels after two enhancements: {}",
             enhanced.count_lit_pixels()
@@ -26,31 +32,131 @@ impl Puzzle for Day20 {
     }
 
     fn part_two(&self) -> String {
-        let enhanced_fifty_times = (0..50).fold(INPUT.clone(), |image, _| {
-            image.enhance(IMAGE_ENHANCEMENT.as_ref())
-        });
+        let mut image = PackedImage::from(&INPUT.image);
+        image.enhance_times(&INPUT.image_enhancement, 50);
         format!(
             "Number of lit pixels after 50 enhancements: {}",
-            enhanced_fifty_times.count_lit_pixels()
+            image.count_lit_pixels()
         )
     }
+
+    fn handle_options(&self, options: &Options) {
+        if let Some(steps) = options.value("steps") {
+            let steps = steps.parse().expect("Steps should be a number");
+            let mut image = PackedImage::from(&INPUT.image);
+            image.enhance_times(&INPUT.image_enhancement, steps);
+            println!(
+                "Number of lit pixels after {} enhancements: {}",
+                steps,
+                image.count_lit_pixels()
+            );
+        }
+
+        if let Some(generations) = options.value("simulate") {
+            let generations = generations.parse().expect("Generations should be a number");
+            if options.has("life") {
+                simulate(Life::new(INPUT.image.lit_pixels()), generations);
+            } else {
+                simulate(
+                    EnhancedImage {
+                        image: INPUT.image.clone(),
+                        image_enhancement: &INPUT.image_enhancement,
+                    },
+                    generations,
+                );
+            }
+        }
+    }
+}
+
+fn simulate<A: CellularAutomaton + Clone + Eq + Hash>(automaton: A, generations: usize) {
+    let mut simulation = Simulation::new(automaton);
+    simulation.run(generations);
+    println!(
+        "Population history: {}",
+        simulation.history().iter().join(", ")
+    );
+    println!(
+        "Population after {} generations: {}",
+        simulation.generation(),
+        simulation.current().population()
+    );
+    match simulation.cycle() {
+        Some(cycle) => println!("Image reaches a {}", cycle),
+        None => println!("No cycle within {} generations", generations),
+    }
 }
 
-fn parse_image_data(data: &str) -> impl Iterator<Item = u8> + '_ {
-    data.chars().map(|c| match c {
-        '.' => 0,
-        '#' => 1,
-        _ => panic!("Invalid character: {}", c),
-    })
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct TrenchMap {
+    image_enhancement: [u8; 512],
+    image: Image,
 }
 
-fn to_array<T: Default + Copy, const N: usize>(data: impl Iterator<Item = T>) -> [T; N] {
-    let mut array = [T::default(); N];
-    data.take(N).enumerate().for_each(|(i, d)| array[i] = d);
-    array
+impl FromStr for TrenchMap {
+    type Err = TrenchMapParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s
+            .lines()
+            .map(str::trim)
+            .enumerate()
+            .map(|(index, data)| (index + 1, data));
+        let image_enhancement = lines
+            .next()
+            .map_or(Ok(Vec::new()), |(line, data)| parse_image_data(line, data))?;
+        let image_enhancement = image_enhancement.try_into().map_err(|data: Vec<u8>| {
+            TrenchMapParsingError::InvalidAlgorithmLength { length: data.len() }
+        })?;
+
+        match lines.next() {
+            Some((_, "")) => {}
+            Some((line, _)) => return Err(TrenchMapParsingError::MissingSeparator { line }),
+            None => return Err(TrenchMapParsingError::MissingSeparator { line: 2 }),
+        }
+
+        let pixels = lines
+            .filter(|(_, data)| !data.is_empty())
+            .map(|(line, data)| parse_image_data(line, data))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            image_enhancement,
+            image: Image { pixels, default: 0 },
+        })
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+enum TrenchMapParsingError {
+    InvalidCharacter {
+        line: usize,
+        column: usize,
+        actual: char,
+    },
+    InvalidAlgorithmLength {
+        length: usize,
+    },
+    MissingSeparator {
+        line: usize,
+    },
+}
+
+fn parse_image_data(line: usize, data: &str) -> Result<Vec<u8>, TrenchMapParsingError> {
+    data.chars()
+        .enumerate()
+        .map(|(index, c)| match c {
+            '.' => Ok(0),
+            '#' => Ok(1),
+            _ => Err(TrenchMapParsingError::InvalidCharacter {
+                line,
+                column: index + 1,
+                actual: c,
+            }),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 struct Image {
     pixels: Vec<Vec<u8>>,
     default: u8,
@@ -115,6 +221,15 @@ impl Image {
         line[x] = pixel;
     }
 
+    fn lit_pixels(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.pixels.iter().enumerate().flat_map(|(y, line)| {
+            line.iter()
+                .enumerate()
+                .filter(|(_, pixel)| **pixel == 1)
+                .map(move |(x, _)| (x as i32, y as i32))
+        })
+    }
+
     pub fn count_lit_pixels(&self) -> usize {
         self.pixels
             .iter()
@@ -124,16 +239,23 @@ impl Image {
     }
 }
 
-impl From<Vec<String>> for Image {
-    fn from(lines: Vec<String>) -> Self {
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+struct EnhancedImage<'a> {
+    image: Image,
+    image_enhancement: &'a [u8; 512],
+}
+
+impl CellularAutomaton for EnhancedImage<'_> {
+    fn step(&self) -> Self {
         Self {
-            pixels: lines
-                .iter()
-                .map(|line| parse_image_data(line).collect())
-                .collect(),
-            default: 0,
+            image: self.image.enhance(self.image_enhancement),
+            image_enhancement: self.image_enhancement,
         }
     }
+
+    fn population(&self) -> usize {
+        self.image.count_lit_pixels()
+    }
 }
 
 impl Display for Image {
@@ -155,11 +277,215 @@ impl Display for Image {
     }
 }
 
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct PackedImage {
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    bits: Vec<u64>,
+    buffer: Vec<u64>,
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+    background: u8,
+}
+
+impl PackedImage {
+    fn with_canvas(width: usize, height: usize, background: u8) -> Self {
+        let words_per_row = width.div_ceil(64);
+        Self {
+            width,
+            height,
+            words_per_row,
+            bits: vec![background_word(background); words_per_row * height],
+            buffer: vec![0; words_per_row * height],
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+            background,
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> u8 {
+        ((self.bits[y * self.words_per_row + x / 64] >> (x % 64)) & 1) as u8
+    }
+
+    fn row(&self, y: usize) -> &[u64] {
+        &self.bits[y * self.words_per_row..(y + 1) * self.words_per_row]
+    }
+
+    fn reserve(&mut self, steps: usize) {
+        let margin = steps + 2;
+        if self.left >= margin
+            && self.top >= margin
+            && self.right + margin <= self.width
+            && self.bottom + margin <= self.height
+        {
+            return;
+        }
+
+        let mut image = Self::with_canvas(
+            self.right - self.left + 2 * margin,
+            self.bottom - self.top + 2 * margin,
+            self.background,
+        );
+        image.left = margin;
+        image.top = margin;
+        image.right = margin + self.right - self.left;
+        image.bottom = margin + self.bottom - self.top;
+        for y in self.top..self.bottom {
+            for x in self.left..self.right {
+                let pixel = self.get(x, y);
+                set_pixel(
+                    &mut image.bits,
+                    image.words_per_row,
+                    x - self.left + margin,
+                    y - self.top + margin,
+                    pixel,
+                );
+            }
+        }
+        *self = image;
+    }
+
+    fn enhance(&mut self, image_enhancement: &[u8]) {
+        self.reserve(1);
+        let background = match self.background {
+            0 => image_enhancement[0],
+            _ => image_enhancement[511],
+        };
+        self.buffer.fill(background_word(background));
+
+        let (left, top, right, bottom) =
+            (self.left - 1, self.top - 1, self.right + 1, self.bottom + 1);
+        let lookup = reversed_lookup(image_enhancement);
+        let outside = background_word(self.background);
+        let mut buffer = std::mem::take(&mut self.buffer);
+        for y in top..bottom {
+            let rows = [self.row(y - 1), self.row(y), self.row(y + 1)];
+            let output = &mut buffer[y * self.words_per_row..(y + 1) * self.words_per_row];
+            for (word, pixels) in output
+                .iter_mut()
+                .enumerate()
+                .take((right - 1) / 64 + 1)
+                .skip(left / 64)
+            {
+                let windows = rows.map(|row| window(row, word, outside));
+                *pixels = [0, 32].iter().fold(0, |pixels, half| {
+                    let [above, row, below] = windows.map(|window| (window >> half) as u64);
+                    (0..32).fold(pixels, |pixels, bit| {
+                        let index = ((above >> bit) & 7) << 6
+                            | ((row >> bit) & 7) << 3
+                            | ((below >> bit) & 7);
+                        pixels | (lookup[index as usize] as u64) << (half + bit)
+                    })
+                });
+            }
+        }
+
+        self.buffer = std::mem::replace(&mut self.bits, buffer);
+        self.left = left;
+        self.top = top;
+        self.right = right;
+        self.bottom = bottom;
+        self.background = background;
+    }
+
+    fn enhance_times(&mut self, image_enhancement: &[u8], steps: usize) {
+        self.reserve(steps);
+        for _ in 0..steps {
+            self.enhance(image_enhancement);
+        }
+    }
+
+    fn count_lit_pixels(&self) -> usize {
+        (self.top..self.bottom)
+            .map(|y| {
+                (self.left..self.right)
+                    .filter(|x| self.get(*x, y) == 1)
+                    .count()
+            })
+            .sum()
+    }
+}
+
+fn window(row: &[u64], word: usize, outside: u64) -> u128 {
+    let previous = word.checked_sub(1).map_or(outside, |word| row[word]);
+    let next = row.get(word + 1).copied().unwrap_or(outside);
+    (previous >> 63) as u128 | (row[word] as u128) << 1 | ((next & 1) as u128) << 65
+}
+
+fn reversed_lookup(image_enhancement: &[u8]) -> [u8; 512] {
+    let reverse_triplets = |index: usize| {
+        (0..9).fold(0, |reversed, bit| {
+            reversed | ((index >> bit) & 1) << (bit / 3 * 3 + 2 - bit % 3)
+        })
+    };
+    let mut lookup = [0; 512];
+    for (index, pixel) in lookup.iter_mut().enumerate() {
+        *pixel = image_enhancement[reverse_triplets(index)];
+    }
+    lookup
+}
+
+fn background_word(background: u8) -> u64 {
+    match background {
+        0 => 0,
+        _ => u64::MAX,
+    }
+}
+
+fn set_pixel(bits: &mut [u64], words_per_row: usize, x: usize, y: usize, pixel: u8) {
+    let word = &mut bits[y * words_per_row + x / 64];
+    match pixel {
+        0 => *word &= !(1 << (x % 64)),
+        _ => *word |= 1 << (x % 64),
+    }
+}
+
+impl From<&Image> for PackedImage {
+    fn from(image: &Image) -> Self {
+        let width = image.pixels.iter().map(Vec::len).max().unwrap_or(0);
+        let height = image.pixels.len();
+        let mut packed = Self::with_canvas(width, height, image.default);
+        packed.right = width;
+        packed.bottom = height;
+        for (y, line) in image.pixels.iter().enumerate() {
+            for x in 0..width {
+                let pixel = *line.get(x).unwrap_or(&image.default);
+                set_pixel(&mut packed.bits, packed.words_per_row, x, y, pixel);
+            }
+        }
+        packed
+    }
+}
+
+impl Display for PackedImage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for y in self.top..self.bottom {
+            for x in self.left..self.right {
+                let char = match self.get(x, y) {
+                    1 => '#',
+                    _ => '.',
+                };
+                f.write_char(char)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
-    static SAMPLE: &str = r"#..#.
+    static SAMPLE: &str = r"..#.#..#####.#.#.#.###.##.....###.##.#..###.####..#####..#....#..#..##..###..######.###...####..#..#####..##..#.#####...##.#.#..#.##..#.#......#.###.######.###.####...#.##.##..#..#..#####.....#.#....###..#.##......#.....#..#..#..##..#...##.######.####.####.#.#...#.......#..#.#.#...####.##.#......#..#...##.#.##..#...##.#.##..###.#......#.#.......#.#.#.####.###.##...#.....####.#..#..#.##.#....##..#.####....##...##..#...#......#.#.......#.......##..####..#...#.#.#...##..#.#..###..#####........#..####......#..#
+
+#..#.
 #....
 ##..#
 ..#..
@@ -167,7 +493,7 @@ mod test {
 
     #[test]
     fn test_parse_and_display_on_sample() {
-        let image: Image = input::read_lines(SAMPLE.as_bytes()).into();
+        let image = SAMPLE.parse::<TrenchMap>().unwrap().image;
 
         assert_eq!(
             &image.to_string(),
@@ -182,10 +508,12 @@ mod test {
 
     #[test]
     fn test_sample_after_one_enhancement() {
-        let enhancement_data: [u8; 512] = to_array(parse_image_data("..#.#..#####.#.#.#.###.##.....###.##.#..###.####..#####..#....#..#..##..###..######.###...####..#..#####..##..#.#####...##.#.#..#.##..#.#......#.###.######.###.####...#.##.##..#..#..#####.....#.#....###..#.##......#.....#..#..#..##..#...##.######.####.####.#.#...#.......#..#.#.#...####.##.#......#..#...##.#.##..#...##.#.##..###.#......#.#.......#.#.#.####.###.##...#.....####.#..#..#.##.#....##..#.####....##...##..#...#......#.#.......#.......##..####..#...#.#.#...##..#.#..###..#####........#..####......#..#"));
-        let image: Image = input::read_lines(SAMPLE.as_bytes()).into();
+        let TrenchMap {
+            image_enhancement,
+            image,
+        } = SAMPLE.parse().unwrap();
 
-        let enhanced_image = image.enhance(&enhancement_data);
+        let enhanced_image = image.enhance(&image_enhancement);
 
         assert_eq!(
             &enhanced_image.to_string(),
@@ -202,10 +530,14 @@ mod test {
 
     #[test]
     fn test_sample_after_two_enhancements() {
-        let enhancement_data: [u8; 512] = to_array(parse_image_data("..#.#..#####.#.#.#.###.##.....###.##.#..###.####..#####..#....#..#..##..###..######.###...####..#..#####..##..#.#####...##.#.#..#.##..#.#......#.###.######.###.####...#.##.##..#..#..#####.....#.#....###..#.##......#.....#..#..#..##..#...##.######.####.####.#.#...#.......#..#.#.#...####.##.#......#..#...##.#.##..#...##.#.##..###.#......#.#.......#.#.#.####.###.##...#.....####.#..#..#.##.#....##..#.####....##...##..#...#......#.#.......#.......##..####..#...#.#.#...##..#.#..###..#####........#..####......#..#"));
-        let image: Image = input::read_lines(SAMPLE.as_bytes()).into();
+        let TrenchMap {
+            image_enhancement,
+            image,
+        } = SAMPLE.parse().unwrap();
 
-        let enhanced_image = image.enhance(&enhancement_data).enhance(&enhancement_data);
+        let enhanced_image = image
+            .enhance(&image_enhancement)
+            .enhance(&image_enhancement);
 
         assert_eq!(
             &enhanced_image.to_string(),
@@ -224,11 +556,128 @@ mod test {
 
     #[test]
     fn count_lit_pixels_should_return_35_for_sample_enhanced_twice() {
-        let enhancement_data: [u8; 512] = to_array(parse_image_data("..#.#..#####.#.#.#.###.##.....###.##.#..###.####..#####..#....#..#..##..###..######.###...####..#..#####..##..#.#####...##.#.#..#.##..#.#......#.###.######.###.####...#.##.##..#..#..#####.....#.#....###..#.##......#.....#..#..#..##..#...##.######.####.####.#.#...#.......#..#.#.#...####.##.#......#..#...##.#.##..#...##.#.##..###.#......#.#.......#.#.#.####.###.##...#.....####.#..#..#.##.#....##..#.####....##...##..#...#......#.#.......#.......##..####..#...#.#.#...##..#.#..###..#####........#..####......#..#"));
-        let image: Image = input::read_lines(SAMPLE.as_bytes()).into();
+        let TrenchMap {
+            image_enhancement,
+            image,
+        } = SAMPLE.parse().unwrap();
 
-        let enhanced_image = image.enhance(&enhancement_data).enhance(&enhancement_data);
+        let enhanced_image = image
+            .enhance(&image_enhancement)
+            .enhance(&image_enhancement);
 
         assert_eq!(enhanced_image.count_lit_pixels(), 35);
     }
+
+    #[test]
+    fn packed_image_should_match_sample_after_two_enhancements() {
+        let TrenchMap {
+            image_enhancement,
+            image,
+        } = SAMPLE.parse().unwrap();
+        let mut packed = PackedImage::from(&image);
+
+        packed.enhance_times(&image_enhancement, 2);
+
+        assert_eq!(
+            &packed.to_string(),
+            r".......#.
+.#..#.#..
+#.#...###
+#...##.#.
+#.....#.#
+.#.#####.
+..#.#####
+...##.##.
+....###..
+"
+        );
+    }
+
+    #[test]
+    fn packed_image_should_count_like_image_on_puzzle_input() {
+        let mut packed = PackedImage::from(&INPUT.image);
+        let mut image = INPUT.image.clone();
+
+        for steps in [2, 50] {
+            while packed.bottom - packed.top < INPUT.image.pixels.len() + 2 * steps {
+                packed.enhance(&INPUT.image_enhancement);
+                image = image.enhance(&INPUT.image_enhancement);
+            }
+
+            assert_eq!(packed.count_lit_pixels(), image.count_lit_pixels());
+        }
+        assert_eq!(packed.count_lit_pixels(), 18516);
+    }
+
+    #[test]
+    fn packed_image_should_grow_its_canvas_when_needed() {
+        let mut packed = PackedImage::from(&INPUT.image);
+        packed.enhance_times(&INPUT.image_enhancement, 2);
+        let count = packed.count_lit_pixels();
+
+        let mut reserved = PackedImage::from(&INPUT.image);
+        reserved.reserve(10);
+        reserved.enhance_times(&INPUT.image_enhancement, 2);
+
+        assert_eq!(reserved.count_lit_pixels(), count);
+        assert_eq!(count, 5819);
+    }
+
+    #[test]
+    fn parse_should_reject_algorithms_of_the_wrong_length() {
+        assert_eq!(
+            "#.#\n\n#..#.".parse::<TrenchMap>(),
+            Err(TrenchMapParsingError::InvalidAlgorithmLength { length: 3 })
+        );
+    }
+
+    #[test]
+    fn parse_should_report_invalid_characters() {
+        let input = SAMPLE.replacen("\n#..#.", "\n#..x.", 1);
+
+        assert_eq!(
+            input.parse::<TrenchMap>(),
+            Err(TrenchMapParsingError::InvalidCharacter {
+                line: 3,
+                column: 4,
+                actual: 'x'
+            })
+        );
+    }
+
+    #[test]
+    fn parse_should_require_a_blank_line_after_the_algorithm() {
+        let input = SAMPLE.replacen("\n\n", "\n", 1);
+
+        assert_eq!(
+            input.parse::<TrenchMap>(),
+            Err(TrenchMapParsingError::MissingSeparator { line: 2 })
+        );
+    }
+
+    #[test]
+    fn simulation_should_track_lit_pixels_of_enhanced_sample() {
+        let TrenchMap {
+            image_enhancement,
+            image,
+        } = SAMPLE.parse().unwrap();
+        let mut simulation = Simulation::new(EnhancedImage {
+            image,
+            image_enhancement: &image_enhancement,
+        });
+
+        simulation.run(2);
+
+        assert_eq!(simulation.history(), &[10, 24, 35]);
+        assert_eq!(simulation.cycle(), None);
+    }
+
+    #[test]
+    fn life_should_start_from_the_lit_pixels_of_an_image() {
+        let image = SAMPLE.parse::<TrenchMap>().unwrap().image;
+
+        let life = Life::new(image.lit_pixels());
+
+        assert_eq!(life.population(), image.count_lit_pixels());
+    }
 }
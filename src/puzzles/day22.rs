@@ -2,15 +2,20 @@ use itertools::Itertools;
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::cmp::{max, min, Ordering};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::ops::Sub;
 use std::str::FromStr;
+use std::time::Instant;
 
-use super::{input, Puzzle};
+use super::{input, Options, Puzzle};
 
 lazy_static! {
     static ref INSTRUCTIONS: Vec<Instruction> = input::read_lines_from_file("day22");
 }
 
+const INITIALIZATION_AREA: Cuboid = Cuboid::new([Range::new(-50, 50); 3]);
+
 pub struct Day22;
 
 impl Puzzle for Day22 {
@@ -19,33 +24,139 @@ impl Puzzle for Day22 {
     }
 
     fn part_one(&self) -> String {
-        let reactor = Reactor::new().execute_all(INSTRUCTIONS.iter().take(20));
+        let reactor = SignedReactor::new().execute_all(INSTRUCTIONS.iter());
         format!(
             "Number of activated cubes in center: {}",
-            reactor.count_activated_cubes()
+            reactor.count_in(&INITIALIZATION_AREA)
         )
     }
 
     fn part_two(&self) -> String {
-        let reactor = Reactor::new().execute_all(INSTRUCTIONS.iter());
+        let reactor = SignedReactor::new().execute_all(INSTRUCTIONS.iter());
         format!(
             "Total number of activated cubes: {}",
             reactor.count_activated_cubes()
         )
     }
+
+    fn handle_options(&self, options: &Options) {
+        if let Some(region) = options.value("slice") {
+            match region.parse().ok().and_then(Plane::new) {
+                Some(plane) => {
+                    let reactor = Reactor::new().execute_all(INSTRUCTIONS.iter());
+                    let mut slice = Slice::new(plane, &reactor);
+                    if options.has("colour") {
+                        slice = slice.coloured_by(&INSTRUCTIONS);
+                    }
+                    match options.value("ppm") {
+                        Some(file) => {
+                            std::fs::write(file, slice.to_ppm()).unwrap();
+                            println!("Slice written to {}", file);
+                        }
+                        None => print!("{}", slice.to_text()),
+                    }
+                }
+                None => println!(
+                    "Invalid slice {}, expected a region one cube thick like x=1..2,y=3..4,z=5..5",
+                    region
+                ),
+            }
+        }
+        if options.has("benchmark") {
+            benchmark("splitting", Reactor::new());
+            benchmark("signed", SignedReactor::new());
+        }
+        if options.has("compact") {
+            let reactor = match options.value("compact") {
+                Some(threshold) => Reactor::new()
+                    .with_compaction_threshold(
+                        threshold.parse().expect("Threshold should be a number"),
+                    )
+                    .execute_all(INSTRUCTIONS.iter()),
+                None => Reactor::new().execute_all(INSTRUCTIONS.iter()).compact(),
+            };
+            for CompactionStats { before, after } in &reactor.compactions {
+                println!("Compacted {} fragments into {}", before, after);
+            }
+            println!(
+                "{} fragments holding {} activated cubes",
+                reactor.cuboids.len(),
+                reactor.count_activated_cubes()
+            );
+        }
+        if options.has("bounds") {
+            match Reactor::new()
+                .execute_all(INSTRUCTIONS.iter())
+                .bounding_box()
+            {
+                Some(bounding_box) => println!("Activated cubes are within {}", bounding_box),
+                None => println!("No cube is activated"),
+            }
+        }
+        if options.has("count-in") || options.has("is-on") {
+            let reactor = SignedReactor::new().execute_all(INSTRUCTIONS.iter());
+            if let Some(region) = options.value("count-in") {
+                match region.parse::<Cuboid>() {
+                    Ok(region) => println!(
+                        "Number of activated cubes in {}: {}",
+                        region,
+                        reactor.count_in(&region)
+                    ),
+                    Err(()) => println!(
+                        "Invalid region {}, expected something like x=1..2,y=3..4,z=5..6",
+                        region
+                    ),
+                }
+            }
+            if let Some(cube) = options.value("is-on") {
+                match parse_cube(cube) {
+                    Some(point) => {
+                        let state = if reactor.is_on(point) { "on" } else { "off" };
+                        println!("Cube {} is {}", cube, state);
+                    }
+                    None => println!("Invalid cube {}, expected something like 1,2,3", cube),
+                }
+            }
+        }
+    }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+fn parse_cube(cube: &str) -> Option<[i64; 3]> {
+    cube.split(',')
+        .map(|c| c.parse().ok())
+        .collect::<Option<Vec<_>>>()?
+        .try_into()
+        .ok()
+}
+
+fn benchmark(name: &str, engine: impl ReactorEngine<3>) {
+    let start = Instant::now();
+    let count = engine
+        .execute_all(INSTRUCTIONS.iter())
+        .count_activated_cubes();
+    println!(
+        "{} engine: {} activated cubes in {}ms",
+        name,
+        count,
+        start.elapsed().as_millis()
+    );
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 struct Range {
     start: i64,
     end: i64,
 }
 
 impl Range {
-    fn new(start: i64, end: i64) -> Self {
+    const fn new(start: i64, end: i64) -> Self {
         Self { start, end }
     }
 
+    fn span(&self, other: &Self) -> Self {
+        Self::new(min(self.start, other.start), max(self.end, other.end))
+    }
+
     fn contains(&self, value: i64) -> bool {
         self.start <= value && value <= self.end
     }
@@ -59,58 +170,58 @@ impl Range {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-struct Cuboid {
-    x: Range,
-    y: Range,
-    z: Range,
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct Cuboid<const N: usize = 3> {
+    ranges: [Range; N],
 }
 
-impl Cuboid {
+impl<const N: usize> Cuboid<N> {
+    const fn new(ranges: [Range; N]) -> Self {
+        Self { ranges }
+    }
+
     fn intersects(&self, other: &Self) -> bool {
-        self.x.intersects(&other.x) && self.y.intersects(&other.y) && self.z.intersects(&other.z)
+        self.ranges
+            .iter()
+            .zip(&other.ranges)
+            .all(|(r1, r2)| r1.intersects(r2))
     }
 
     fn intersection(&self, other: &Self) -> Option<Self> {
         if self.intersects(other) {
-            Some(Self {
-                x: Range::new(
-                    max(self.x.start, other.x.start),
-                    min(self.x.end, other.x.end),
-                ),
-                y: Range::new(
-                    max(self.y.start, other.y.start),
-                    min(self.y.end, other.y.end),
-                ),
-                z: Range::new(
-                    max(self.z.start, other.z.start),
-                    min(self.z.end, other.z.end),
-                ),
-            })
+            Some(Self::new(std::array::from_fn(|axis| {
+                Range::new(
+                    max(self.ranges[axis].start, other.ranges[axis].start),
+                    min(self.ranges[axis].end, other.ranges[axis].end),
+                )
+            })))
         } else {
             None
         }
     }
 
     fn len(&self) -> usize {
-        self.x.len() * self.y.len() * self.z.len()
+        self.ranges.iter().map(Range::len).product()
+    }
+
+    fn span(&self, other: &Self) -> Self {
+        Self::new(std::array::from_fn(|axis| {
+            self.ranges[axis].span(&other.ranges[axis])
+        }))
     }
 }
 
-impl Sub for Cuboid {
-    type Output = Vec<Cuboid>;
+impl<const N: usize> Sub for Cuboid<N> {
+    type Output = Vec<Cuboid<N>>;
 
     fn sub(self, rhs: Self) -> Self::Output {
         if let Some(intersection) = self.intersection(&rhs) {
-            let x_ranges = divide_range(self.x, rhs.x);
-            let y_ranges = divide_range(self.y, rhs.y);
-            let z_ranges = divide_range(self.z, rhs.z);
-
-            x_ranges
-                .into_iter()
-                .cartesian_product(y_ranges)
-                .cartesian_product(z_ranges)
-                .map(|((x, y), z)| Self { x, y, z })
+            self.ranges
+                .iter()
+                .zip(&rhs.ranges)
+                .map(|(r1, r2)| divide_range(*r1, *r2).into_iter())
+                .multi_cartesian_product()
+                .map(|ranges| Self::new(ranges.try_into().unwrap()))
                 .filter(|cuboid| cuboid != &intersection)
                 .collect()
         } else {
@@ -139,84 +250,446 @@ fn divide_range(r1: Range, r2: Range) -> Vec<Range> {
     }
 }
 
+fn axis_names(dimensions: usize) -> Vec<char> {
+    if dimensions <= 3 {
+        "xyz".chars().take(dimensions).collect()
+    } else {
+        ('a'..='z')
+            .skip(26usize.saturating_sub(dimensions))
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
-enum Instruction {
-    On(Cuboid),
-    Off(Cuboid),
+enum Instruction<const N: usize = 3> {
+    On(Cuboid<N>),
+    Off(Cuboid<N>),
+    Toggle(Cuboid<N>),
+}
+
+impl<const N: usize> Instruction<N> {
+    fn cuboid(&self) -> Cuboid<N> {
+        match *self {
+            Instruction::On(cuboid) | Instruction::Off(cuboid) | Instruction::Toggle(cuboid) => {
+                cuboid
+            }
+        }
+    }
 }
 
-impl FromStr for Instruction {
+impl<const N: usize> FromStr for Cuboid<N> {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         lazy_static! {
-            static ref INSTRUCTION_REGEX: Regex = Regex::new(r"^(?P<status>on|off) x=(?P<x_start>-?\d+)\.\.(?P<x_end>-?\d+),y=(?P<y_start>-?\d+)\.\.(?P<y_end>-?\d+),z=(?P<z_start>-?\d+)\.\.(?P<z_end>-?\d+)$").unwrap();
+            static ref RANGE_REGEX: Regex =
+                Regex::new(r"^(?P<axis>[a-z])=(?P<start>-?\d+)\.\.(?P<end>-?\d+)$").unwrap();
         }
 
-        let capture = INSTRUCTION_REGEX.captures(s).unwrap();
-
-        let cuboid = Cuboid {
-            x: Range::new(
-                capture.name("x_start").unwrap().as_str().parse().unwrap(),
-                capture.name("x_end").unwrap().as_str().parse().unwrap(),
-            ),
-            y: Range::new(
-                capture.name("y_start").unwrap().as_str().parse().unwrap(),
-                capture.name("y_end").unwrap().as_str().parse().unwrap(),
-            ),
-            z: Range::new(
-                capture.name("z_start").unwrap().as_str().parse().unwrap(),
-                capture.name("z_end").unwrap().as_str().parse().unwrap(),
-            ),
-        };
+        let parts = s.split(',').collect::<Vec<_>>();
+        let axes = axis_names(N);
+        if parts.len() != N || axes.len() != N {
+            return Err(());
+        }
+
+        let ranges = parts
+            .into_iter()
+            .zip(axes)
+            .map(|(part, axis)| {
+                let capture = RANGE_REGEX.captures(part).ok_or(())?;
+                if !capture.name("axis").unwrap().as_str().starts_with(axis) {
+                    return Err(());
+                }
+                Ok(Range::new(
+                    capture
+                        .name("start")
+                        .unwrap()
+                        .as_str()
+                        .parse()
+                        .map_err(|_| ())?,
+                    capture
+                        .name("end")
+                        .unwrap()
+                        .as_str()
+                        .parse()
+                        .map_err(|_| ())?,
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Cuboid::new(ranges.try_into().unwrap()))
+    }
+}
+
+impl<const N: usize> Display for Cuboid<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let ranges = axis_names(N)
+            .into_iter()
+            .zip(&self.ranges)
+            .map(|(axis, range)| format!("{}={}..{}", axis, range.start, range.end))
+            .join(",");
+        write!(f, "{}", ranges)
+    }
+}
+
+impl<const N: usize> FromStr for Instruction<N> {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        lazy_static! {
+            static ref INSTRUCTION_REGEX: Regex =
+                Regex::new(r"^(?P<status>on|off|toggle) (?P<cuboid>.+)$").unwrap();
+        }
+
+        let capture = INSTRUCTION_REGEX.captures(s).ok_or(())?;
+
+        let cuboid = capture.name("cuboid").unwrap().as_str().parse()?;
 
         match capture.name("status").unwrap().as_str() {
             "on" => Ok(Instruction::On(cuboid)),
+            "toggle" => Ok(Instruction::Toggle(cuboid)),
             _ => Ok(Instruction::Off(cuboid)),
         }
     }
 }
 
+trait ReactorEngine<const N: usize> {
+    fn execute(self, instruction: &Instruction<N>) -> Self;
+
+    fn count_activated_cubes(&self) -> usize;
+
+    fn count_in(&self, region: &Cuboid<N>) -> usize;
+
+    fn is_on(&self, point: [i64; N]) -> bool {
+        let cube = Cuboid::new(point.map(|coordinate| Range::new(coordinate, coordinate)));
+        self.count_in(&cube) == 1
+    }
+
+    fn execute_all<'a>(self, instructions: impl Iterator<Item = &'a Instruction<N>>) -> Self
+    where
+        Self: Sized,
+    {
+        instructions
+            .into_iter()
+            .fold(self, |reactor, instruction| reactor.execute(instruction))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct CompactionStats {
+    before: usize,
+    after: usize,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
-struct Reactor {
-    cuboids: Vec<Cuboid>,
+struct Reactor<const N: usize = 3> {
+    cuboids: Vec<Cuboid<N>>,
+    compaction_threshold: Option<usize>,
+    compactions: Vec<CompactionStats>,
 }
 
-impl Reactor {
+impl<const N: usize> Reactor<N> {
     fn new() -> Self {
         Self {
             cuboids: Vec::new(),
+            compaction_threshold: None,
+            compactions: Vec::new(),
         }
     }
 
-    fn execute(self, instruction: &Instruction) -> Self {
-        match *instruction {
-            Instruction::On(cuboid) => {
-                let mut cuboids: Vec<_> =
-                    self.cuboids.into_iter().flat_map(|c| c - cuboid).collect();
-                cuboids.push(cuboid);
-                Self { cuboids }
-            }
-            Instruction::Off(cuboid) => Self {
-                cuboids: self.cuboids.into_iter().flat_map(|c| c - cuboid).collect(),
-            },
+    fn with_compaction_threshold(self, threshold: usize) -> Self {
+        Self {
+            compaction_threshold: Some(threshold),
+            ..self
         }
     }
 
-    fn execute_all<'a>(self, instructions: impl Iterator<Item = &'a Instruction>) -> Self {
-        instructions
-            .into_iter()
-            .fold(self, |reactor, instruction| reactor.execute(instruction))
+    fn bounding_box(&self) -> Option<Cuboid<N>> {
+        self.cuboids
+            .iter()
+            .copied()
+            .reduce(|bounding_box, cuboid| bounding_box.span(&cuboid))
+    }
+
+    fn compact(mut self) -> Self {
+        let before = self.cuboids.len();
+        self.cuboids = compact_cuboids(self.cuboids);
+        self.compactions.push(CompactionStats {
+            before,
+            after: self.cuboids.len(),
+        });
+        self
+    }
+}
+
+fn compact_cuboids<const N: usize>(mut cuboids: Vec<Cuboid<N>>) -> Vec<Cuboid<N>> {
+    loop {
+        let count = cuboids.len();
+        for axis in 0..N {
+            cuboids = merge_along_axis(cuboids, axis);
+        }
+        if cuboids.len() == count {
+            return cuboids;
+        }
+    }
+}
+
+fn merge_along_axis<const N: usize>(cuboids: Vec<Cuboid<N>>, axis: usize) -> Vec<Cuboid<N>> {
+    cuboids
+        .into_iter()
+        .map(|cuboid| cuboid.ranges)
+        .into_group_map_by(|ranges| {
+            let mut key = *ranges;
+            key[axis] = Range::new(0, 0);
+            key
+        })
+        .into_values()
+        .flat_map(|mut group| {
+            group.sort_by_key(|ranges| ranges[axis].start);
+            group.into_iter().coalesce(|mut previous, current| {
+                if previous[axis].end + 1 == current[axis].start {
+                    previous[axis].end = current[axis].end;
+                    Ok(previous)
+                } else {
+                    Err((previous, current))
+                }
+            })
+        })
+        .map(Cuboid::new)
+        .collect()
+}
+
+impl<const N: usize> ReactorEngine<N> for Reactor<N> {
+    fn execute(self, instruction: &Instruction<N>) -> Self {
+        let cuboid = instruction.cuboid();
+        let mut cuboids: Vec<_> = self.cuboids.iter().flat_map(|c| *c - cuboid).collect();
+        match instruction {
+            Instruction::On(_) => cuboids.push(cuboid),
+            Instruction::Toggle(_) => {
+                cuboids.extend(self.cuboids.iter().fold(vec![cuboid], |parts, c| {
+                    parts.into_iter().flat_map(|part| part - *c).collect()
+                }))
+            }
+            Instruction::Off(_) => {}
+        }
+        let reactor = Self { cuboids, ..self };
+        match reactor.compaction_threshold {
+            Some(threshold) if reactor.cuboids.len() > threshold => reactor.compact(),
+            _ => reactor,
+        }
     }
 
     fn count_activated_cubes(&self) -> usize {
         self.cuboids.iter().map(Cuboid::len).sum()
     }
+
+    fn count_in(&self, region: &Cuboid<N>) -> usize {
+        self.cuboids
+            .iter()
+            .filter_map(|cuboid| cuboid.intersection(region))
+            .map(|intersection| intersection.len())
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct SignedReactor<const N: usize = 3> {
+    cuboids: HashMap<Cuboid<N>, i64>,
+}
+
+impl<const N: usize> SignedReactor<N> {
+    fn new() -> Self {
+        Self {
+            cuboids: HashMap::new(),
+        }
+    }
+}
+
+impl<const N: usize> ReactorEngine<N> for SignedReactor<N> {
+    fn execute(mut self, instruction: &Instruction<N>) -> Self {
+        let cuboid = instruction.cuboid();
+        let factor = match instruction {
+            Instruction::Toggle(_) => 2,
+            _ => 1,
+        };
+        let intersections = self
+            .cuboids
+            .iter()
+            .filter_map(|(c, count)| c.intersection(&cuboid).map(|i| (i, *count)))
+            .collect::<Vec<_>>();
+        for (intersection, count) in intersections {
+            *self.cuboids.entry(intersection).or_insert(0) -= factor * count;
+        }
+        if let Instruction::On(_) | Instruction::Toggle(_) = instruction {
+            *self.cuboids.entry(cuboid).or_insert(0) += 1;
+        }
+        self.cuboids.retain(|_, count| *count != 0);
+        self
+    }
+
+    fn count_activated_cubes(&self) -> usize {
+        self.cuboids
+            .iter()
+            .map(|(cuboid, count)| cuboid.len() as i64 * count)
+            .sum::<i64>() as usize
+    }
+
+    fn count_in(&self, region: &Cuboid<N>) -> usize {
+        self.cuboids
+            .iter()
+            .filter_map(|(cuboid, count)| cuboid.intersection(region).map(|i| (i, count)))
+            .map(|(intersection, count)| intersection.len() as i64 * count)
+            .sum::<i64>() as usize
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Plane {
+    region: Cuboid,
+    axis: usize,
+}
+
+impl Plane {
+    fn new(region: Cuboid) -> Option<Self> {
+        region
+            .ranges
+            .iter()
+            .rposition(|range| range.len() == 1)
+            .map(|axis| Self { region, axis })
+    }
+
+    fn columns(&self) -> Range {
+        self.region.ranges[if self.axis == 0 { 1 } else { 0 }]
+    }
+
+    fn rows(&self) -> Range {
+        self.region.ranges[if self.axis == 2 { 1 } else { 2 }]
+    }
+
+    fn project(&self, cuboid: &Cuboid) -> Option<(Range, Range)> {
+        cuboid.intersection(&self.region).map(|intersection| {
+            let plane = Plane {
+                region: intersection,
+                axis: self.axis,
+            };
+            (plane.columns(), plane.rows())
+        })
+    }
+
+    fn fill<T: Clone>(&self, grid: &mut [Vec<T>], cuboid: &Cuboid, value: T) {
+        if let Some((columns, rows)) = self.project(cuboid) {
+            for row in rows.start..=rows.end {
+                for column in columns.start..=columns.end {
+                    grid[(row - self.rows().start) as usize]
+                        [(column - self.columns().start) as usize] = value.clone();
+                }
+            }
+        }
+    }
+
+    fn grid<T: Clone>(&self, value: T) -> Vec<Vec<T>> {
+        vec![vec![value; self.columns().len()]; self.rows().len()]
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Slice {
+    plane: Plane,
+    lit: Vec<Vec<bool>>,
+    last_instructions: Option<Vec<Vec<Option<usize>>>>,
+}
+
+const PALETTE: [[u8; 3]; 8] = [
+    [230, 25, 75],
+    [60, 180, 75],
+    [255, 225, 25],
+    [0, 130, 200],
+    [245, 130, 48],
+    [145, 30, 180],
+    [70, 240, 240],
+    [240, 50, 230],
+];
+
+impl Slice {
+    fn new(plane: Plane, reactor: &Reactor) -> Self {
+        let mut lit = plane.grid(false);
+        for cuboid in &reactor.cuboids {
+            plane.fill(&mut lit, cuboid, true);
+        }
+        Self {
+            plane,
+            lit,
+            last_instructions: None,
+        }
+    }
+
+    fn coloured_by(self, instructions: &[Instruction]) -> Self {
+        let mut last_instructions = self.plane.grid(None);
+        for (i, instruction) in instructions.iter().enumerate() {
+            self.plane
+                .fill(&mut last_instructions, &instruction.cuboid(), Some(i));
+        }
+        Self {
+            last_instructions: Some(last_instructions),
+            ..self
+        }
+    }
+
+    fn cells(&self) -> impl Iterator<Item = impl Iterator<Item = Option<Option<usize>>> + '_> + '_ {
+        self.lit.iter().enumerate().map(move |(row, cells)| {
+            cells.iter().enumerate().map(move |(column, lit)| {
+                lit.then(|| {
+                    self.last_instructions
+                        .as_ref()
+                        .and_then(|last_instructions| last_instructions[row][column])
+                })
+            })
+        })
+    }
+
+    fn to_text(&self) -> String {
+        self.cells()
+            .map(|row| {
+                row.map(|cell| match cell {
+                    None => '.',
+                    Some(None) => '#',
+                    Some(Some(i)) => std::char::from_digit((i % 36) as u32, 36).unwrap(),
+                })
+                .collect::<String>()
+            })
+            .map(|row| row + "\n")
+            .collect()
+    }
+
+    fn to_ppm(&self) -> String {
+        let pixels = self
+            .cells()
+            .map(|row| {
+                row.map(|cell| {
+                    let [r, g, b] = match cell {
+                        None => [0, 0, 0],
+                        Some(None) => [255, 255, 255],
+                        Some(Some(i)) => PALETTE[i % PALETTE.len()],
+                    };
+                    format!("{} {} {}", r, g, b)
+                })
+                .join(" ")
+            })
+            .join("\n");
+        format!(
+            "P3\n{} {}\n255\n{}\n",
+            self.plane.columns().len(),
+            self.plane.rows().len(),
+            pixels
+        )
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
+    use std::collections::HashSet;
 
     lazy_static! {
         static ref SAMPLE_INSTRUCTIONS: Vec<Instruction> = input::read_lines(
@@ -238,100 +711,400 @@ on x=10..10,y=10..10,z=10..10
         assert_eq!(result.count_activated_cubes(), 39);
     }
 
+    #[test]
+    fn signed_reactor_should_activate_39_cubes_with_sample_instructions() {
+        let reactor = SignedReactor::new();
+
+        let result = reactor.execute_all(SAMPLE_INSTRUCTIONS.iter());
+
+        assert_eq!(result.count_activated_cubes(), 39);
+    }
+
+    #[test]
+    fn both_engines_should_agree_on_puzzle_input() {
+        let splitting = Reactor::new().execute_all(INSTRUCTIONS.iter());
+        let signed = SignedReactor::new().execute_all(INSTRUCTIONS.iter());
+
+        assert_eq!(
+            splitting.count_activated_cubes(),
+            signed.count_activated_cubes()
+        );
+    }
+
+    #[test]
+    fn both_engines_should_agree_after_each_instruction_of_puzzle_input() {
+        INSTRUCTIONS
+            .iter()
+            .take(60)
+            .scan(
+                (Reactor::new(), SignedReactor::new()),
+                |(splitting, signed), instruction| {
+                    *splitting = splitting.clone().execute(instruction);
+                    *signed = signed.clone().execute(instruction);
+                    Some((
+                        splitting.count_activated_cubes(),
+                        signed.count_activated_cubes(),
+                    ))
+                },
+            )
+            .for_each(|(splitting, signed)| assert_eq!(splitting, signed));
+    }
+
+    #[test]
+    fn count_in_should_only_count_cubes_inside_the_region() {
+        let region = Cuboid::new([Range::new(9, 11), Range::new(9, 11), Range::new(9, 11)]);
+
+        let splitting = Reactor::new().execute_all(SAMPLE_INSTRUCTIONS.iter());
+        let signed = SignedReactor::new().execute_all(SAMPLE_INSTRUCTIONS.iter());
+
+        assert_eq!(splitting.count_in(&region), 1);
+        assert_eq!(signed.count_in(&region), 1);
+    }
+
+    fn assert_sample_cubes_activation(reactor: &impl ReactorEngine<3>) {
+        assert!(reactor.is_on([10, 10, 10]));
+        assert!(!reactor.is_on([11, 11, 11]));
+        assert!(reactor.is_on([13, 13, 13]));
+        assert!(!reactor.is_on([14, 13, 13]));
+    }
+
+    #[test]
+    fn is_on_should_tell_whether_a_cube_is_activated() {
+        assert_sample_cubes_activation(&Reactor::new().execute_all(SAMPLE_INSTRUCTIONS.iter()));
+        assert_sample_cubes_activation(
+            &SignedReactor::new().execute_all(SAMPLE_INSTRUCTIONS.iter()),
+        );
+    }
+
+    #[test]
+    fn parse_cube_should_only_accept_three_numbers() {
+        assert_eq!(parse_cube("1,-2,3"), Some([1, -2, 3]));
+        assert_eq!(parse_cube("1,2"), None);
+        assert_eq!(parse_cube("1,2,3,4"), None);
+        assert_eq!(parse_cube("1,2,x"), None);
+    }
+
+    #[test]
+    fn bounding_box_should_enclose_all_activated_cubes() {
+        let reactor = Reactor::new().execute_all(SAMPLE_INSTRUCTIONS.iter());
+
+        assert_eq!(
+            reactor.bounding_box(),
+            Some(Cuboid::new([
+                Range::new(10, 13),
+                Range::new(10, 13),
+                Range::new(10, 13),
+            ]))
+        );
+        assert_eq!(Reactor::<3>::new().bounding_box(), None);
+    }
+
+    #[test]
+    fn count_in_initialization_area_should_match_instructions_clipped_to_it() {
+        let clipped_instructions = INSTRUCTIONS
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::On(cuboid) => cuboid
+                    .intersection(&INITIALIZATION_AREA)
+                    .map(Instruction::On),
+                Instruction::Off(cuboid) => cuboid
+                    .intersection(&INITIALIZATION_AREA)
+                    .map(Instruction::Off),
+                Instruction::Toggle(cuboid) => cuboid
+                    .intersection(&INITIALIZATION_AREA)
+                    .map(Instruction::Toggle),
+            })
+            .collect::<Vec<_>>();
+
+        let clipped = Reactor::new().execute_all(clipped_instructions.iter());
+        let reactor = SignedReactor::new().execute_all(INSTRUCTIONS.iter());
+
+        assert_eq!(
+            reactor.count_in(&INITIALIZATION_AREA),
+            clipped.count_activated_cubes()
+        );
+    }
+
+    #[test]
+    fn compact_should_merge_adjacent_cuboids_sharing_two_ranges() {
+        let reactor = Reactor::new()
+            .execute_all(
+                [
+                    "on x=0..1,y=0..1,z=0..1",
+                    "on x=2..3,y=0..1,z=0..1",
+                    "on x=0..3,y=2..2,z=0..1",
+                    "on x=10..11,y=0..1,z=0..1",
+                ]
+                .iter()
+                .map(|line| line.parse().unwrap())
+                .collect::<Vec<Instruction>>()
+                .iter(),
+            )
+            .compact();
+
+        assert_eq!(
+            reactor
+                .cuboids
+                .iter()
+                .sorted_by_key(|c| c.ranges[0].start)
+                .collect::<Vec<_>>(),
+            vec![
+                &"x=0..3,y=0..2,z=0..1".parse::<Cuboid>().unwrap(),
+                &"x=10..11,y=0..1,z=0..1".parse::<Cuboid>().unwrap(),
+            ]
+        );
+        assert_eq!(
+            reactor.compactions,
+            vec![CompactionStats {
+                before: 4,
+                after: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn compact_should_preserve_activated_cubes_of_puzzle_input() {
+        let reactor = Reactor::new().execute_all(INSTRUCTIONS.iter());
+        let count = reactor.count_activated_cubes();
+
+        let compacted = reactor.compact();
+
+        assert_eq!(compacted.count_activated_cubes(), count);
+        assert!(compacted.compactions[0].after < compacted.compactions[0].before);
+    }
+
+    #[test]
+    fn reactor_should_compact_automatically_past_threshold() {
+        let reactor = Reactor::new()
+            .with_compaction_threshold(4000)
+            .execute_all(INSTRUCTIONS.iter());
+
+        assert!(!reactor.compactions.is_empty());
+        assert!(reactor
+            .compactions
+            .iter()
+            .all(|stats| stats.before > 4000 && stats.after <= stats.before));
+        assert_eq!(reactor.count_activated_cubes(), 1125649856443608);
+    }
+
+    #[test]
+    fn cuboid_should_be_displayed_like_in_instructions() {
+        let cuboid: Cuboid = "x=-20..26,y=-36..17,z=-47..7".parse().unwrap();
+
+        assert_eq!(cuboid.to_string(), "x=-20..26,y=-36..17,z=-47..7");
+        assert_eq!("x=1..2,y=3..4".parse::<Cuboid>(), Err(()));
+        assert_eq!("a=1..2,b=3..4,c=5..6".parse::<Cuboid>(), Err(()));
+    }
+
+    #[test]
+    fn cuboids_of_other_dimensions_should_name_their_axes() {
+        let square: Cuboid<2> = "x=0..9,y=-3..4".parse().unwrap();
+        let tesseract: Cuboid<4> = "w=0..1,x=2..3,y=4..5,z=6..7".parse().unwrap();
+
+        assert_eq!(square.len(), 80);
+        assert_eq!(square.to_string(), "x=0..9,y=-3..4");
+        assert_eq!(tesseract.len(), 16);
+        assert_eq!(tesseract.to_string(), "w=0..1,x=2..3,y=4..5,z=6..7");
+        assert_eq!("x=0..1,y=2..3,z=4..5".parse::<Cuboid<4>>(), Err(()));
+    }
+
+    #[test]
+    fn instructions_with_the_wrong_number_of_axes_should_be_rejected() {
+        assert_eq!("on x=1..2,y=3..4".parse::<Instruction>(), Err(()));
+        assert_eq!("on x=1..2,y=3..4,z=5..6".parse::<Instruction<2>>(), Err(()));
+        assert_eq!("flip x=1..2,y=3..4,z=5..6".parse::<Instruction>(), Err(()));
+    }
+
+    #[test]
+    fn cuboids_with_more_axes_than_letters_should_be_rejected() {
+        let axes = (0..27).map(|_| "a=0..1").join(",");
+
+        assert_eq!(axes.parse::<Cuboid<27>>(), Err(()));
+    }
+
+    fn sample_slice() -> Slice {
+        let plane = Plane::new("x=9..13,y=9..13,z=10..10".parse().unwrap()).unwrap();
+        let reactor = Reactor::new().execute_all(SAMPLE_INSTRUCTIONS.iter());
+        Slice::new(plane, &reactor)
+    }
+
+    #[test]
+    fn plane_should_be_orthogonal_to_the_last_single_cube_axis() {
+        let plane = Plane::new("x=0..0,y=-3..4,z=0..9".parse().unwrap()).unwrap();
+
+        assert_eq!(plane.axis, 0);
+        assert_eq!(plane.columns(), Range::new(-3, 4));
+        assert_eq!(plane.rows(), Range::new(0, 9));
+        assert_eq!(Plane::new("x=0..1,y=0..1,z=0..1".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn slice_should_render_lit_cubes_as_text() {
+        assert_eq!(
+            sample_slice().to_text(),
+            ".....\n\
+             .#.#.\n\
+             ...#.\n\
+             .###.\n\
+             .....\n"
+        );
+    }
+
+    #[test]
+    fn slice_should_render_last_instruction_of_lit_cubes() {
+        let slice = sample_slice().coloured_by(&SAMPLE_INSTRUCTIONS);
+
+        assert_eq!(
+            slice.to_text(),
+            ".....\n\
+             .3.0.\n\
+             ...0.\n\
+             .000.\n\
+             .....\n"
+        );
+    }
+
+    #[test]
+    fn slice_should_render_as_ppm() {
+        let ppm = sample_slice().coloured_by(&SAMPLE_INSTRUCTIONS).to_ppm();
+
+        let lines = ppm.lines().collect::<Vec<_>>();
+        assert_eq!(lines[..3], ["P3", "5 5", "255"]);
+        assert_eq!(lines.len(), 3 + 5);
+        assert_eq!(lines[4], "0 0 0 0 130 200 0 0 0 230 25 75 0 0 0");
+    }
+
+    fn parse_instructions<const N: usize>(lines: &[&str]) -> Vec<Instruction<N>> {
+        lines.iter().map(|line| line.parse().unwrap()).collect()
+    }
+
+    fn count_with_both_engines<const N: usize>(instructions: &[Instruction<N>]) -> (usize, usize) {
+        (
+            Reactor::new()
+                .execute_all(instructions.iter())
+                .count_activated_cubes(),
+            SignedReactor::new()
+                .execute_all(instructions.iter())
+                .count_activated_cubes(),
+        )
+    }
+
+    #[test]
+    fn toggle_should_flip_every_cube_of_the_cuboid() {
+        let instructions =
+            parse_instructions::<3>(&["on x=0..2,y=0..2,z=0..2", "toggle x=1..3,y=1..3,z=1..3"]);
+
+        assert_eq!(count_with_both_engines(&instructions), (38, 38));
+    }
+
+    #[test]
+    fn reactor_should_handle_two_dimensional_instructions() {
+        let instructions = parse_instructions::<2>(&["on x=0..9,y=0..9", "toggle x=5..14,y=5..14"]);
+
+        assert_eq!(count_with_both_engines(&instructions), (150, 150));
+    }
+
+    #[test]
+    fn reactor_should_handle_four_dimensional_instructions() {
+        let instructions = parse_instructions::<4>(&[
+            "on w=0..1,x=0..1,y=0..1,z=0..1",
+            "off w=0..0,x=0..1,y=0..1,z=0..1",
+            "toggle w=1..2,x=0..0,y=0..0,z=0..0",
+        ]);
+
+        assert_eq!(count_with_both_engines(&instructions), (8, 8));
+        let reactor = SignedReactor::new().execute_all(instructions.iter());
+        assert!(!reactor.is_on([1, 0, 0, 0]));
+        assert!(reactor.is_on([2, 0, 0, 0]));
+    }
+
+    fn arbitrary_instruction() -> impl Strategy<Value = Instruction<2>> {
+        (0..3, 0i64..8, 0i64..4, 0i64..8, 0i64..4).prop_map(|(kind, x, width, y, height)| {
+            let cuboid = Cuboid::new([Range::new(x, x + width), Range::new(y, y + height)]);
+            match kind {
+                0 => Instruction::On(cuboid),
+                1 => Instruction::Off(cuboid),
+                _ => Instruction::Toggle(cuboid),
+            }
+        })
+    }
+
+    fn count_cell_by_cell(instructions: &[Instruction<2>]) -> usize {
+        let mut cells = HashSet::new();
+        for instruction in instructions {
+            let [x, y] = instruction.cuboid().ranges;
+            for cell in (x.start..=x.end).cartesian_product(y.start..=y.end) {
+                match instruction {
+                    Instruction::On(_) => {
+                        cells.insert(cell);
+                    }
+                    Instruction::Off(_) => {
+                        cells.remove(&cell);
+                    }
+                    Instruction::Toggle(_) => {
+                        if !cells.remove(&cell) {
+                            cells.insert(cell);
+                        }
+                    }
+                }
+            }
+        }
+        cells.len()
+    }
+
+    proptest! {
+        #[test]
+        fn both_engines_should_count_like_a_cell_by_cell_simulation(
+            instructions in prop::collection::vec(arbitrary_instruction(), 0..12)
+        ) {
+            let expected = count_cell_by_cell(&instructions);
+
+            prop_assert_eq!(count_with_both_engines(&instructions), (expected, expected));
+        }
+    }
+
     #[test]
     fn test_intersection() {
-        let c1 = Cuboid {
-            x: Range::new(0, 3),
-            y: Range::new(0, 3),
-            z: Range::new(0, 3),
-        };
-        let c2 = Cuboid {
-            x: Range::new(2, 4),
-            y: Range::new(2, 4),
-            z: Range::new(2, 4),
-        };
+        let c1 = Cuboid::new([Range::new(0, 3), Range::new(0, 3), Range::new(0, 3)]);
+        let c2 = Cuboid::new([Range::new(2, 4), Range::new(2, 4), Range::new(2, 4)]);
 
         let intersection = c1.intersection(&c2);
 
         assert_eq!(
             intersection,
-            Some(Cuboid {
-                x: Range::new(2, 3),
-                y: Range::new(2, 3),
-                z: Range::new(2, 3),
-            })
+            Some(Cuboid::new([
+                Range::new(2, 3),
+                Range::new(2, 3),
+                Range::new(2, 3),
+            ]))
         );
     }
 
     #[test]
     fn test_subtraction_with_intersection_on_each_axis() {
-        let c1 = Cuboid {
-            x: Range::new(0, 3),
-            y: Range::new(0, 3),
-            z: Range::new(0, 3),
-        };
-        let c2 = Cuboid {
-            x: Range::new(2, 4),
-            y: Range::new(2, 4),
-            z: Range::new(2, 4),
-        };
+        let c1 = Cuboid::new([Range::new(0, 3), Range::new(0, 3), Range::new(0, 3)]);
+        let c2 = Cuboid::new([Range::new(2, 4), Range::new(2, 4), Range::new(2, 4)]);
 
         let result = c1 - c2;
 
         assert_eq!(
             result,
             vec![
-                Cuboid {
-                    x: Range::new(0, 1),
-                    y: Range::new(0, 1),
-                    z: Range::new(0, 1),
-                },
-                Cuboid {
-                    x: Range::new(0, 1),
-                    y: Range::new(0, 1),
-                    z: Range::new(2, 3),
-                },
-                Cuboid {
-                    x: Range::new(0, 1),
-                    y: Range::new(2, 3),
-                    z: Range::new(0, 1),
-                },
-                Cuboid {
-                    x: Range::new(0, 1),
-                    y: Range::new(2, 3),
-                    z: Range::new(2, 3),
-                },
-                Cuboid {
-                    x: Range::new(2, 3),
-                    y: Range::new(0, 1),
-                    z: Range::new(0, 1),
-                },
-                Cuboid {
-                    x: Range::new(2, 3),
-                    y: Range::new(0, 1),
-                    z: Range::new(2, 3),
-                },
-                Cuboid {
-                    x: Range::new(2, 3),
-                    y: Range::new(2, 3),
-                    z: Range::new(0, 1),
-                },
+                Cuboid::new([Range::new(0, 1), Range::new(0, 1), Range::new(0, 1),]),
+                Cuboid::new([Range::new(0, 1), Range::new(0, 1), Range::new(2, 3),]),
+                Cuboid::new([Range::new(0, 1), Range::new(2, 3), Range::new(0, 1),]),
+                Cuboid::new([Range::new(0, 1), Range::new(2, 3), Range::new(2, 3),]),
+                Cuboid::new([Range::new(2, 3), Range::new(0, 1), Range::new(0, 1),]),
+                Cuboid::new([Range::new(2, 3), Range::new(0, 1), Range::new(2, 3),]),
+                Cuboid::new([Range::new(2, 3), Range::new(2, 3), Range::new(0, 1),]),
             ]
         );
     }
 
     #[test]
     fn test_subtraction_empty_result() {
-        let c1 = Cuboid {
-            x: Range::new(0, 3),
-            y: Range::new(0, 3),
-            z: Range::new(0, 3),
-        };
-        let c2 = Cuboid {
-            x: Range::new(0, 4),
-            y: Range::new(0, 4),
-            z: Range::new(0, 4),
-        };
+        let c1 = Cuboid::new([Range::new(0, 3), Range::new(0, 3), Range::new(0, 3)]);
+        let c2 = Cuboid::new([Range::new(0, 4), Range::new(0, 4), Range::new(0, 4)]);
 
         let result = c1 - c2;
 
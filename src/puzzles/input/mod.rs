@@ -30,8 +30,6 @@ where
         .filter(Result::is_ok)
         .map(|line| line.unwrap().trim().to_string())
         .filter(|line| !line.is_empty())
-        .map(|line| line.parse())
-        .filter(Result::is_ok)
-        .map(Result::unwrap)
+        .flat_map(|line| line.parse())
         .collect()
 }
@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 use std::time::Instant;
 
+mod automaton;
 mod input;
+mod registration;
 
 mod day01;
 mod day02;
@@ -40,6 +42,35 @@ pub trait Puzzle {
         let duration = start.elapsed();
         println!("Done in {}ms", duration.as_millis());
     }
+
+    fn handle_options(&self, _options: &Options) {}
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct Options(HashMap<String, Option<String>>);
+
+impl Options {
+    pub fn has(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.0.get(name).and_then(Option::as_deref)
+    }
+}
+
+impl FromIterator<String> for Options {
+    fn from_iter<I: IntoIterator<Item = String>>(args: I) -> Self {
+        let mut options = HashMap::new();
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                let value = args.next_if(|next| !next.starts_with("--"));
+                options.insert(name.to_string(), value);
+            }
+        }
+        Self(options)
+    }
 }
 
 pub fn puzzles() -> HashMap<u8, Box<dyn Puzzle>> {
@@ -71,3 +102,22 @@ pub fn puzzles() -> HashMap<u8, Box<dyn Puzzle>> {
     .map(|puzzle| (puzzle.number(), puzzle))
     .collect()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn options_should_parse_flags_with_and_without_values() {
+        let options: Options = ["--dot", "caves.dot", "--trace", "--highlight", "3"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        assert_eq!(options.value("dot"), Some("caves.dot"));
+        assert!(options.has("trace"));
+        assert_eq!(options.value("trace"), None);
+        assert_eq!(options.value("highlight"), Some("3"));
+        assert!(!options.has("missing"));
+    }
+}
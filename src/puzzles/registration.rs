@@ -0,0 +1,836 @@
+use itertools::Itertools;
+use std::cmp::{min, Reverse};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Position {
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn manhattan_distance(&self, other: &Position) -> i32 {
+        (self.x - other.x).abs() + (self.y - other.y).abs() + (self.z - other.z).abs()
+    }
+
+    pub fn square_distance(&self, other: &Position) -> i32 {
+        (self.x - other.x).pow(2) + (self.y - other.y).pow(2) + (self.z - other.z).pow(2)
+    }
+
+    fn coordinates(&self) -> [i32; 3] {
+        [self.x, self.y, self.z]
+    }
+}
+
+impl Add for Position {
+    type Output = Position;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Position {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+impl Sub for Position {
+    type Output = Position;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Position {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+impl Neg for Position {
+    type Output = Position;
+
+    fn neg(self) -> Self::Output {
+        Position {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
+impl FromIterator<i32> for Position {
+    fn from_iter<T: IntoIterator<Item = i32>>(iter: T) -> Self {
+        let mut iter = iter.into_iter();
+        Self {
+            x: iter.next().unwrap(),
+            y: iter.next().unwrap(),
+            z: iter.next().unwrap(),
+        }
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{}", self.x, self.y, self.z)
+    }
+}
+
+impl From<&str> for Position {
+    fn from(line: &str) -> Self {
+        line.split(',')
+            .take(3)
+            .map(|s| s.trim().parse::<i32>().unwrap())
+            .collect()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Rotation([[i32; 3]; 3]);
+
+impl Rotation {
+    pub const IDENTITY: Rotation = Rotation([[1, 0, 0], [0, 1, 0], [0, 0, 1]]);
+
+    pub fn new(matrix: [[i32; 3]; 3]) -> Option<Self> {
+        let rotation = Self(matrix);
+        (rotation * rotation.inverse() == Self::IDENTITY && rotation.determinant() == 1)
+            .then_some(rotation)
+    }
+
+    pub fn all() -> Vec<Rotation> {
+        (0..3)
+            .permutations(3)
+            .cartesian_product((0..3).map(|_| [-1, 1]).multi_cartesian_product())
+            .map(|(axes, signs)| {
+                let mut matrix = [[0; 3]; 3];
+                for row in 0..3 {
+                    matrix[row][axes[row]] = signs[row];
+                }
+                matrix
+            })
+            .filter_map(Self::new)
+            .collect()
+    }
+
+    pub fn matrix(&self) -> [[i32; 3]; 3] {
+        self.0
+    }
+
+    pub fn inverse(&self) -> Self {
+        let mut matrix = [[0; 3]; 3];
+        for (row, column) in (0..3).cartesian_product(0..3) {
+            matrix[row][column] = self.0[column][row];
+        }
+        Self(matrix)
+    }
+
+    fn determinant(&self) -> i32 {
+        let m = self.0;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+}
+
+impl Display for Rotation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let rows = self
+            .0
+            .iter()
+            .map(|row| row.iter().join(","))
+            .collect::<Vec<_>>();
+        write!(f, "[{}]", rows.join(";"))
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s
+            .trim()
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .split(';')
+            .map(|row| {
+                row.split(',')
+                    .map(|value| value.trim().parse::<i32>().map_err(|_| ()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .and_then(|row| <[i32; 3]>::try_from(row).map_err(|_| ()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let matrix = <[[i32; 3]; 3]>::try_from(rows).map_err(|_| ())?;
+        Self::new(matrix).ok_or(())
+    }
+}
+
+impl Mul for Rotation {
+    type Output = Rotation;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut matrix = [[0; 3]; 3];
+        for (row, column) in (0..3).cartesian_product(0..3) {
+            matrix[row][column] = (0..3).map(|i| self.0[row][i] * rhs.0[i][column]).sum();
+        }
+        Self(matrix)
+    }
+}
+
+impl Mul<Position> for Rotation {
+    type Output = Position;
+
+    fn mul(self, rhs: Position) -> Self::Output {
+        let coordinates = rhs.coordinates();
+        self.0
+            .iter()
+            .map(|row| (0..3).map(|i| row[i] * coordinates[i]).sum())
+            .collect()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Transform {
+    pub rotation: Rotation,
+    pub translation: Position,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        rotation: Rotation::IDENTITY,
+        translation: Position::new(0, 0, 0),
+    };
+
+    pub fn new(rotation: Rotation, translation: Position) -> Self {
+        Self {
+            rotation,
+            translation,
+        }
+    }
+
+    pub fn apply(&self, position: Position) -> Position {
+        self.rotation * position + self.translation
+    }
+
+    pub fn then(&self, other: &Transform) -> Transform {
+        Transform {
+            rotation: other.rotation * self.rotation,
+            translation: other.apply(self.translation),
+        }
+    }
+
+    pub fn inverse(&self) -> Transform {
+        let rotation = self.rotation.inverse();
+        Transform {
+            rotation,
+            translation: -(rotation * self.translation),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PointCloud(HashMap<Position, HashMap<i32, usize>>);
+
+impl PointCloud {
+    pub fn new(points: Vec<Position>) -> Self {
+        Self(
+            points
+                .iter()
+                .map(|point| (*point, get_distances(point, &points)))
+                .collect(),
+        )
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = &Position> {
+        self.0.keys()
+    }
+
+    #[cfg(test)]
+    pub fn contains(&self, point: &Position) -> bool {
+        self.0.contains_key(point)
+    }
+
+    pub fn find_common_points(
+        &self,
+        other: &PointCloud,
+        overlap: usize,
+    ) -> Option<HashMap<Position, Position>> {
+        let common_points = self
+            .0
+            .iter()
+            .flat_map(|(point, distances)| {
+                other
+                    .0
+                    .iter()
+                    .map(|(other_point, other_distances)| {
+                        (
+                            count_common_distances(distances, other_distances),
+                            *other_point,
+                        )
+                    })
+                    .filter(|(common_distances, _)| *common_distances >= overlap - 1)
+                    .max_by_key(|&(common_distances, other_point)| {
+                        (common_distances, Reverse(other_point))
+                    })
+                    .map(|(_, other_point)| (*point, other_point))
+            })
+            .collect::<HashMap<_, _>>();
+
+        (common_points.len() >= overlap).then_some(common_points)
+    }
+
+    fn fingerprint(&self) -> HashMap<i32, usize> {
+        self.0
+            .keys()
+            .tuple_combinations()
+            .map(|(a, b)| a.square_distance(b))
+            .counts()
+    }
+
+    pub fn transform(&self, transform: &Transform) -> Self {
+        Self(
+            self.0
+                .iter()
+                .map(|(point, distances)| (transform.apply(*point), distances.clone()))
+                .collect(),
+        )
+    }
+}
+
+fn get_distances(point: &Position, points: &[Position]) -> HashMap<i32, usize> {
+    points
+        .iter()
+        .filter(|p| point != *p)
+        .map(|p| point.square_distance(p))
+        .counts()
+}
+
+fn count_common_distances(d1: &HashMap<i32, usize>, d2: &HashMap<i32, usize>) -> usize {
+    d1.iter()
+        .map(|(distance, count)| min(*count, d2.get(distance).copied().unwrap_or(0)))
+        .sum()
+}
+
+pub fn find_transform(
+    common_points: &HashMap<Position, Position>,
+    tolerance: f64,
+) -> Option<(Transform, Vec<Position>)> {
+    let min_inliers = ((1.0 - tolerance) * common_points.len() as f64).ceil() as usize;
+    Rotation::all()
+        .into_iter()
+        .map(|rotation| {
+            let (translation, inliers) = vote_translation(common_points, rotation);
+            (Transform::new(rotation, translation), inliers)
+        })
+        .max_by_key(|(_, inliers)| inliers.len())
+        .filter(|(_, inliers)| !inliers.is_empty() && inliers.len() >= min_inliers)
+}
+
+fn vote_translation(
+    common_points: &HashMap<Position, Position>,
+    rotation: Rotation,
+) -> (Position, Vec<Position>) {
+    common_points
+        .iter()
+        .map(|(point, &reference_point)| (reference_point - rotation * *point, *point))
+        .into_group_map()
+        .into_iter()
+        .max_by_key(|(translation, points)| (points.len(), Reverse(*translation)))
+        .map(|(translation, points)| (translation, points.into_iter().sorted().collect()))
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Alignment {
+    pub transform: Transform,
+    pub reference: Option<usize>,
+    pub relative: Transform,
+    pub inliers: Vec<Position>,
+}
+
+impl Alignment {
+    const REFERENCE: Alignment = Alignment {
+        transform: Transform::IDENTITY,
+        reference: None,
+        relative: Transform::IDENTITY,
+        inliers: Vec::new(),
+    };
+}
+
+pub fn transformation_chain(
+    alignments: &[Alignment],
+    cloud: usize,
+) -> Vec<(usize, usize, Transform)> {
+    let mut chain = Vec::new();
+    let mut current = cloud;
+    while let Some(reference) = alignments[current].reference {
+        chain.push((current, reference, alignments[current].relative));
+        current = reference;
+    }
+    chain
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlignmentError {
+    Disconnected { clouds: Vec<usize> },
+    InvalidOverlap { overlap: usize },
+    InvalidTolerance { tolerance: f64 },
+}
+
+struct FingerprintIndex {
+    fingerprints: Vec<HashMap<i32, usize>>,
+    clouds_by_distance: HashMap<i32, Vec<usize>>,
+}
+
+impl FingerprintIndex {
+    fn new(clouds: &[PointCloud]) -> Self {
+        let fingerprints = clouds
+            .iter()
+            .map(PointCloud::fingerprint)
+            .collect::<Vec<_>>();
+        let mut clouds_by_distance: HashMap<i32, Vec<usize>> = HashMap::new();
+        for (i, fingerprint) in fingerprints.iter().enumerate() {
+            for distance in fingerprint.keys() {
+                clouds_by_distance.entry(*distance).or_default().push(i);
+            }
+        }
+
+        Self {
+            fingerprints,
+            clouds_by_distance,
+        }
+    }
+
+    fn candidates(&self, cloud: usize, min_shared_distances: usize) -> Vec<usize> {
+        let mut shared_distances = HashMap::new();
+        for (distance, count) in &self.fingerprints[cloud] {
+            for other in &self.clouds_by_distance[distance] {
+                if *other != cloud {
+                    *shared_distances.entry(*other).or_insert(0) +=
+                        min(*count, self.fingerprints[*other][distance]);
+                }
+            }
+        }
+
+        shared_distances
+            .into_iter()
+            .filter(|(_, count)| *count >= min_shared_distances)
+            .map(|(other, _)| other)
+            .sorted()
+            .collect()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aligner {
+    overlap: usize,
+    tolerance: f64,
+}
+
+impl Default for Aligner {
+    fn default() -> Self {
+        Self {
+            overlap: 12,
+            tolerance: 0.0,
+        }
+    }
+}
+
+impl Aligner {
+    pub fn with_overlap(self, overlap: usize) -> Result<Self, AlignmentError> {
+        if overlap < 2 {
+            return Err(AlignmentError::InvalidOverlap { overlap });
+        }
+        Ok(Self { overlap, ..self })
+    }
+
+    pub fn with_tolerance(self, tolerance: f64) -> Result<Self, AlignmentError> {
+        if !(0.0..=1.0).contains(&tolerance) {
+            return Err(AlignmentError::InvalidTolerance { tolerance });
+        }
+        Ok(Self { tolerance, ..self })
+    }
+
+    fn min_matching_points(&self) -> usize {
+        let min_matching_points = ((1.0 - self.tolerance) * self.overlap as f64).ceil() as usize;
+        min_matching_points.clamp(2, self.overlap)
+    }
+
+    pub fn align(&self, clouds: &[PointCloud]) -> Result<Vec<Alignment>, AlignmentError> {
+        let index = FingerprintIndex::new(clouds);
+        let min_matching_points = self.min_matching_points();
+        let min_shared_distances = min_matching_points * (min_matching_points - 1) / 2;
+        let mut alignments: Vec<Option<Alignment>> = vec![None; clouds.len()];
+        let mut to_visit = VecDeque::new();
+        if !clouds.is_empty() {
+            alignments[0] = Some(Alignment::REFERENCE);
+            to_visit.push_back(0);
+        }
+
+        while let Some(j) = to_visit.pop_front() {
+            let reference_transform = alignments[j].as_ref().unwrap().transform;
+            for i in index.candidates(j, min_shared_distances) {
+                if alignments[i].is_some() {
+                    continue;
+                }
+                if let Some((relative, inliers)) =
+                    self.find_relative_transform(&clouds[i], &clouds[j])
+                {
+                    alignments[i] = Some(Alignment {
+                        transform: relative.then(&reference_transform),
+                        reference: Some(j),
+                        relative,
+                        inliers,
+                    });
+                    to_visit.push_back(i);
+                }
+            }
+        }
+
+        let disconnected = alignments
+            .iter()
+            .positions(|alignment| alignment.is_none())
+            .collect::<Vec<_>>();
+        if !disconnected.is_empty() {
+            return Err(AlignmentError::Disconnected {
+                clouds: disconnected,
+            });
+        }
+
+        Ok(alignments.into_iter().flatten().collect())
+    }
+
+    fn find_relative_transform(
+        &self,
+        cloud: &PointCloud,
+        reference: &PointCloud,
+    ) -> Option<(Transform, Vec<Position>)> {
+        cloud
+            .find_common_points(reference, self.min_matching_points())
+            .and_then(|common_points| find_transform(&common_points, self.tolerance))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn there_should_be_24_distinct_rotations() {
+        let rotations = Rotation::all();
+
+        assert_eq!(rotations.len(), 24);
+        assert!(rotations.iter().all_unique());
+        assert!(rotations.contains(&Rotation::IDENTITY));
+    }
+
+    #[test]
+    fn rotations_should_be_closed_under_composition() {
+        let rotations = Rotation::all();
+
+        assert!(rotations
+            .iter()
+            .cartesian_product(&rotations)
+            .all(|(r1, r2)| rotations.contains(&(*r1 * *r2))));
+    }
+
+    #[test]
+    fn rotation_times_its_inverse_should_be_identity() {
+        assert!(Rotation::all()
+            .iter()
+            .all(|rotation| *rotation * rotation.inverse() == Rotation::IDENTITY));
+    }
+
+    #[test]
+    fn rotation_should_rotate_positions() {
+        let rotation = Rotation::new([[0, -1, 0], [1, 0, 0], [0, 0, 1]]).unwrap();
+
+        assert_eq!(rotation * Position::new(1, 2, 3), Position::new(-2, 1, 3));
+    }
+
+    #[test]
+    fn rotation_should_parse_its_display() {
+        let rotation = Rotation::new([[0, -1, 0], [1, 0, 0], [0, 0, 1]]).unwrap();
+
+        assert_eq!(rotation.to_string().parse(), Ok(rotation));
+        assert_eq!("[-1,0,0;0,1,0;0,0,1]".parse::<Rotation>(), Err(()));
+        assert_eq!("[1,0,0;0,1,0]".parse::<Rotation>(), Err(()));
+    }
+
+    #[test]
+    fn rotation_should_display_its_rows() {
+        let rotation = Rotation::new([[0, -1, 0], [1, 0, 0], [0, 0, 1]]).unwrap();
+
+        assert_eq!(rotation.to_string(), "[0,-1,0;1,0,0;0,0,1]");
+    }
+
+    #[test]
+    fn new_rotation_should_reject_reflections_and_non_orthogonal_matrices() {
+        assert_eq!(Rotation::new([[-1, 0, 0], [0, 1, 0], [0, 0, 1]]), None);
+        assert_eq!(Rotation::new([[1, 1, 0], [0, 1, 0], [0, 0, 1]]), None);
+    }
+
+    #[test]
+    fn transform_then_inverse_should_be_identity() {
+        let transform = Transform::new(Rotation::all()[7], Position::new(68, -1246, -43));
+
+        let result = transform.then(&transform.inverse());
+
+        assert_eq!(result, Transform::IDENTITY);
+    }
+
+    #[test]
+    fn composed_transforms_should_apply_in_order() {
+        let first = Transform::new(Rotation::all()[3], Position::new(1, 2, 3));
+        let second = Transform::new(Rotation::all()[17], Position::new(-5, 8, 13));
+        let position = Position::new(404, -588, -901);
+
+        let result = first.then(&second).apply(position);
+
+        assert_eq!(result, second.apply(first.apply(position)));
+    }
+
+    fn sample_points(range: std::ops::Range<i32>) -> Vec<Position> {
+        range
+            .map(|i| {
+                Position::new(
+                    i * i * 17 % 1000 - 500,
+                    i * i * i * 31 % 1000 - 500,
+                    i * 73 % 1000 - 500,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn align_should_recover_transform_of_overlapping_cloud() {
+        let hidden = Transform::new(Rotation::all()[13], Position::new(-1200, 40, 350));
+        let cloud = sample_points(3..18)
+            .into_iter()
+            .map(|point| hidden.apply(point))
+            .collect();
+        let clouds = [
+            PointCloud::new(sample_points(0..15)),
+            PointCloud::new(cloud),
+        ];
+
+        let alignments = Aligner::default().align(&clouds).unwrap();
+
+        assert_eq!(
+            alignments,
+            vec![
+                Alignment::REFERENCE,
+                Alignment {
+                    transform: hidden.inverse(),
+                    reference: Some(0),
+                    relative: hidden.inverse(),
+                    inliers: sample_points(3..15)
+                        .into_iter()
+                        .map(|point| hidden.apply(point))
+                        .sorted()
+                        .collect(),
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn align_should_report_clouds_disconnected_from_the_first_one() {
+        let clouds = [
+            PointCloud::new(sample_points(0..15)),
+            PointCloud::new(sample_points(20..35)),
+            PointCloud::new(sample_points(3..18)),
+        ];
+
+        let result = Aligner::default().align(&clouds);
+
+        assert_eq!(
+            result,
+            Err(AlignmentError::Disconnected { clouds: vec![1] })
+        );
+    }
+
+    #[test]
+    fn fingerprint_index_should_only_propose_clouds_sharing_enough_distances() {
+        let clouds = [
+            PointCloud::new(sample_points(0..15)),
+            PointCloud::new(sample_points(20..35)),
+            PointCloud::new(sample_points(3..18)),
+        ];
+        let index = FingerprintIndex::new(&clouds);
+
+        assert_eq!(index.candidates(0, 66), vec![2]);
+        assert_eq!(index.candidates(1, 66), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn align_should_match_overlaps_with_repeated_distances() {
+        let mut points = sample_points(0..10);
+        points.push(points[0] + Position::new(300, 400, 0));
+        points.push(points[0] + Position::new(0, 0, 500));
+        let hidden = Transform::new(Rotation::all()[9], Position::new(250, -700, 80));
+        let cloud = points
+            .iter()
+            .chain(&sample_points(40..45))
+            .map(|point| hidden.apply(*point))
+            .collect();
+        let clouds = [
+            PointCloud::new(
+                points
+                    .iter()
+                    .copied()
+                    .chain(sample_points(20..25))
+                    .collect(),
+            ),
+            PointCloud::new(cloud),
+        ];
+
+        let alignments = Aligner::default().align(&clouds).unwrap();
+
+        assert_eq!(alignments[1].transform, hidden.inverse());
+        assert_eq!(alignments[1].inliers.len(), 12);
+    }
+
+    #[test]
+    fn aligner_should_reject_overlaps_below_two() {
+        assert_eq!(
+            Aligner::default().with_overlap(1),
+            Err(AlignmentError::InvalidOverlap { overlap: 1 })
+        );
+        assert!(Aligner::default().with_overlap(2).is_ok());
+    }
+
+    #[test]
+    fn aligner_should_reject_tolerances_outside_zero_to_one() {
+        assert_eq!(
+            Aligner::default().with_tolerance(-0.1),
+            Err(AlignmentError::InvalidTolerance { tolerance: -0.1 })
+        );
+        assert_eq!(
+            Aligner::default().with_tolerance(1.5),
+            Err(AlignmentError::InvalidTolerance { tolerance: 1.5 })
+        );
+        assert!(matches!(
+            Aligner::default().with_tolerance(f64::NAN),
+            Err(AlignmentError::InvalidTolerance { tolerance }) if tolerance.is_nan()
+        ));
+        assert!(Aligner::default().with_tolerance(0.0).is_ok());
+        assert!(Aligner::default().with_tolerance(1.0).is_ok());
+    }
+
+    #[test]
+    fn vote_translation_should_break_ties_with_the_smallest_translation() {
+        for _ in 0..20 {
+            let common_points = HashMap::from([
+                (Position::new(0, 0, 0), Position::new(5, 0, 0)),
+                (Position::new(1, 0, 0), Position::new(1, 0, 0)),
+                (Position::new(2, 0, 0), Position::new(2, 3, 0)),
+            ]);
+
+            let result = vote_translation(&common_points, Rotation::IDENTITY);
+
+            assert_eq!(
+                result,
+                (Position::new(0, 0, 0), vec![Position::new(1, 0, 0)])
+            );
+        }
+    }
+
+    #[test]
+    fn find_common_points_should_pick_the_best_match() {
+        let points = vec![
+            Position::new(0, 0, 0),
+            Position::new(1, 0, 0),
+            Position::new(0, 2, 0),
+        ];
+        let mut other_points = points.clone();
+        other_points.push(Position::new(1, 1, 0));
+
+        for _ in 0..20 {
+            let cloud = PointCloud::new(points.clone());
+            let other = PointCloud::new(other_points.clone());
+
+            let common_points = cloud.find_common_points(&other, 2).unwrap();
+
+            assert_eq!(
+                common_points,
+                points.iter().map(|point| (*point, *point)).collect()
+            );
+        }
+    }
+
+    #[test]
+    fn align_within_tolerance_should_skip_a_noisy_point() {
+        let hidden = Transform::new(Rotation::all()[21], Position::new(-90, 600, -1300));
+        let mut cloud = sample_points(3..18)
+            .into_iter()
+            .map(|point| hidden.apply(point))
+            .collect::<Vec<_>>();
+        cloud[4] = cloud[4] + Position::new(1, 0, 0);
+        let clouds = [
+            PointCloud::new(sample_points(0..15)),
+            PointCloud::new(cloud),
+        ];
+
+        let without_tolerance = Aligner::default().align(&clouds);
+        let alignments = Aligner::default()
+            .with_tolerance(0.25)
+            .unwrap()
+            .align(&clouds)
+            .unwrap();
+
+        assert_eq!(
+            without_tolerance,
+            Err(AlignmentError::Disconnected { clouds: vec![1] })
+        );
+        assert_eq!(alignments[1].transform, hidden.inverse());
+        assert_eq!(alignments[1].inliers.len(), 11);
+    }
+
+    fn common_points_with_outlier(hidden: &Transform) -> HashMap<Position, Position> {
+        let mut common_points = sample_points(0..12)
+            .into_iter()
+            .map(|point| (hidden.apply(point), point))
+            .collect::<HashMap<_, _>>();
+        common_points.insert(Position::new(1, 2, 3), Position::new(-4, 5, 6));
+        common_points
+    }
+
+    #[test]
+    fn find_transform_should_reject_inconsistent_match_without_tolerance() {
+        let hidden = Transform::new(Rotation::all()[5], Position::new(300, -20, 1000));
+
+        let result = find_transform(&common_points_with_outlier(&hidden), 0.0);
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn find_transform_should_vote_out_inconsistent_match_within_tolerance() {
+        let hidden = Transform::new(Rotation::all()[5], Position::new(300, -20, 1000));
+
+        let (transform, inliers) =
+            find_transform(&common_points_with_outlier(&hidden), 0.1).unwrap();
+
+        assert_eq!(transform, hidden.inverse());
+        assert_eq!(inliers.len(), 12);
+        assert!(!inliers.contains(&Position::new(1, 2, 3)));
+    }
+
+    #[test]
+    fn find_transform_should_fail_when_outliers_exceed_tolerance() {
+        let hidden = Transform::new(Rotation::all()[5], Position::new(300, -20, 1000));
+        let mut common_points = common_points_with_outlier(&hidden);
+        common_points.insert(Position::new(7, 8, 9), Position::new(0, 0, 0));
+
+        let result = find_transform(&common_points, 0.1);
+
+        assert_eq!(result, None);
+    }
+}